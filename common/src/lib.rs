@@ -15,6 +15,7 @@ pub struct FrameData {
 	pub checkpoint_sub: u8,
 	pub timer_wave: u32,
 	pub multiplier_one: u32,
+	pub multiplier_two: u32,
 	pub dynamic_rank: f32,
 	pub pattern_rank: f32,
 }
@@ -104,6 +104,7 @@ fn get_frame_data() -> FrameData {
 	let checkpoint_sub = read_var(c"checkpoint_sub").unwrap().value as u8;
 	let timer_wave = read_var(c"timer_wave").unwrap().value as u32;
 	let multiplier_one = read_var(c"multiplier_one").unwrap().value as u32;
+	let multiplier_two = read_var(c"multiplier_two").map_or(0, |v| v.value as u32);
 	let pattern_rank = read_var(c"pattern_rank").unwrap().value as f32;
 	let dynamic_rank = read_var(c"dynamic_rank").unwrap().value as f32;
 	FrameData {
@@ -117,6 +118,7 @@ fn get_frame_data() -> FrameData {
 		checkpoint_sub,
 		timer_wave,
 		multiplier_one,
+		multiplier_two,
 		pattern_rank,
 		dynamic_rank,
 	}
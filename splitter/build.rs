@@ -1,4 +1,4 @@
-use std::{env, io};
+use std::{env, fs, io, path::Path};
 
 use winresource::WindowsResource;
 
@@ -9,5 +9,33 @@ fn main() -> io::Result<()> {
 			.set_icon("assets/icon.ico")
 			.compile()?;
 	}
-	Ok(())
+	generate_migrations()
+}
+
+/// Collect `sql/migrations/*.sql` into a list for the database to apply in order.
+/// Each file name starts with the schema version it migrates to, e.g. `0005_add_notes.sql`.
+fn generate_migrations() -> io::Result<()> {
+	println!("cargo:rerun-if-changed=sql/migrations");
+
+	let mut files = fs::read_dir("sql/migrations")?
+		.map(|entry| entry.map(|e| e.path()))
+		.collect::<io::Result<Vec<_>>>()?;
+	files.retain(|path| path.extension().is_some_and(|ext| ext == "sql"));
+	files.sort();
+
+	let mut out = String::from("&[\n");
+	for (idx, path) in files.iter().enumerate() {
+		let name = path.file_stem().unwrap().to_string_lossy();
+		let version = name.split('_').next().and_then(|v| v.parse::<usize>().ok());
+		if version != Some(idx + 1) {
+			panic!(
+				"Migration {name} is out of sequence, expected it to start with {:04}",
+				idx + 1
+			);
+		}
+		out += &format!("\t({name:?}, include_str!({:?})),\n", fs::canonicalize(path)?);
+	}
+	out += "]\n";
+
+	fs::write(Path::new(&env::var_os("OUT_DIR").unwrap()).join("migrations.rs"), out)
 }
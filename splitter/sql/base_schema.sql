@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS categories (
+    id          INTEGER PRIMARY KEY,
+    name        TEXT UNIQUE NOT NULL,
+    mode        INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS runs (
+    id          INTEGER PRIMARY KEY,
+    category    INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS splits (
+    id          INTEGER PRIMARY KEY,
+    split_num   INTEGER NOT NULL,
+    score       INTEGER NOT NULL,
+    hits        INTEGER,
+    mult        INTEGER,
+    run_id      INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE
+);
\ No newline at end of file
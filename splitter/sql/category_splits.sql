@@ -0,0 +1,7 @@
+SELECT runs.id, runs.datetime, split_num, score, mult, pattern_rank, dynamic_rank, final, imported, peak_mult, mult_p2, peak_mult_p2
+FROM splits
+INNER JOIN runs
+INNER JOIN categories
+ON splits.run_id = runs.id AND runs.category = categories.id
+WHERE categories.name = ?1
+ORDER BY runs.id, split_num
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE splits ADD COLUMN final BOOLEAN;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE runs ADD COLUMN datetime INTEGER;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE splits ADD COLUMN pattern_rank REAL;
+ALTER TABLE splits ADD COLUMN dynamic_rank REAL;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE runs ADD COLUMN imported BOOLEAN;
\ No newline at end of file
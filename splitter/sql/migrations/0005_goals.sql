@@ -0,0 +1,14 @@
+CREATE TABLE goals (
+    id          INTEGER PRIMARY KEY,
+    category    INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    name        TEXT NOT NULL,
+    target      INTEGER NOT NULL,
+    UNIQUE(category, name)
+);
+
+CREATE TABLE goal_splits (
+    id          INTEGER PRIMARY KEY,
+    goal_id     INTEGER NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    split_num   INTEGER NOT NULL,
+    score       INTEGER NOT NULL
+);
\ No newline at end of file
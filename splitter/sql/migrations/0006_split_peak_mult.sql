@@ -0,0 +1,3 @@
+ALTER TABLE splits ADD COLUMN peak_mult INTEGER;
+ALTER TABLE splits ADD COLUMN mult_p2 INTEGER;
+ALTER TABLE splits ADD COLUMN peak_mult_p2 INTEGER;
\ No newline at end of file
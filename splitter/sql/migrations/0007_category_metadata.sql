@@ -0,0 +1,6 @@
+ALTER TABLE categories ADD COLUMN notes TEXT NOT NULL DEFAULT '';
+ALTER TABLE categories ADD COLUMN game_version TEXT NOT NULL DEFAULT '';
+ALTER TABLE categories ADD COLUMN ruleset TEXT NOT NULL DEFAULT '';
+ALTER TABLE categories ADD COLUMN color INTEGER;
+ALTER TABLE categories ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN archived BOOLEAN NOT NULL DEFAULT false;
\ No newline at end of file
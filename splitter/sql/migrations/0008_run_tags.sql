@@ -0,0 +1,12 @@
+CREATE TABLE run_tags (
+    id          INTEGER PRIMARY KEY,
+    run_id      INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    tag         TEXT NOT NULL,
+    UNIQUE(run_id, tag)
+);
+
+CREATE TABLE split_notes (
+    id          INTEGER PRIMARY KEY,
+    split_id    INTEGER NOT NULL UNIQUE REFERENCES splits(id) ON DELETE CASCADE,
+    note        TEXT NOT NULL
+);
\ No newline at end of file
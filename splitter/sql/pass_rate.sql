@@ -1,8 +1,12 @@
 SELECT
 split_num,
-count(case when final = false then 1 end) as pass_count,
-count(case when final = false or final=true then 1 end) as run_count,
-count(case when final = false then 1 end) * 100.0 / count(case when final = false or final=true then 1 end) as percentage
+count(case when final = false or final = true then 1 end) as reached,
+count(case when final = false then 1 end) as passed,
+count(case when final = true then 1 end) as ended
 FROM splits INNER JOIN runs ON runs.id = splits.run_id
-WHERE score > 0 and runs.category = 3
-GROUP BY split_num
\ No newline at end of file
+WHERE score > 0 AND runs.category = ?1
+AND (?2 IS NULL OR date(runs.datetime, 'localtime') >= ?2)
+AND (?3 IS NULL OR date(runs.datetime, 'localtime') <= ?3)
+AND (?4 IS NULL OR runs.id IN (SELECT run_id FROM run_tags WHERE tag = ?4))
+GROUP BY split_num
+ORDER BY split_num
\ No newline at end of file
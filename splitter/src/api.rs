@@ -0,0 +1,364 @@
+use std::{
+	io::Cursor,
+	sync::{
+		Arc, Mutex,
+		mpsc::{self, Receiver, SyncSender, TrySendError},
+	},
+	thread,
+};
+
+use log::{debug, info, warn};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::{Message, WebSocket, handshake::derive_accept_key, protocol::Role};
+
+use crate::{
+	Gamemode, ZeroError, ZeroSplitter,
+	events::RunEvent,
+	split_name,
+	theme::{BLACK, DARK_GREEN, DARK_ORANGE, DARKER_GREEN, DARKER_ORANGE, GREEN, GREENEST, LIGHT_ORANGE, WHITE},
+};
+
+/// Browser source for OBS, served on `/overlay`. `/* PALETTE */` is replaced with the theme's colors.
+const OVERLAY: &str = include_str!("../assets/overlay.html");
+
+/// Events a WebSocket client can fall behind by before it's dropped
+const CLIENT_QUEUE: usize = 64;
+
+/// Queue of events for a WebSocket client, sent by the client's own thread
+type Client = SyncSender<String>;
+
+/// What the window shows, as served by the HTTP API
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiveState {
+	pub category: String,
+	pub mode: Gamemode,
+	/// Whether the game is sending frames
+	pub hooked: bool,
+	pub active: bool,
+	pub current_split: Option<usize>,
+	pub current_split_name: Option<String>,
+	pub score: i32,
+	pub comparison: String,
+	pub comparison_splits: Vec<i32>,
+	pub splits: Vec<SplitState>,
+	pub pb: i32,
+	pub pb_splits: Vec<i32>,
+	pub sum_of_best: i32,
+	pub gold_splits: Vec<i32>,
+	pub attempts: usize,
+}
+
+/// One split of the current run next to the comparison
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SplitState {
+	pub split: usize,
+	pub name: String,
+	/// Score gained during the split
+	pub score: i32,
+	/// Running total at the end of the split
+	pub total: i32,
+	pub comparison: i32,
+	pub comparison_total: i32,
+	/// `total - comparison_total`, for splits the run has reached
+	pub delta: Option<i32>,
+	/// Best score ever gained during the split
+	pub best: i32,
+}
+
+impl LiveState {
+	pub fn new(splitter: &ZeroSplitter) -> Self {
+		let categories = &splitter.categories;
+		let category = categories.current();
+		let stats = categories.stats();
+		let current_split = splitter.run.current_split().ok();
+		// Without a run, list the splits with nothing scored yet
+		let scores = splitter.run.scores().unwrap_or_else(|_| match category.mode {
+			Gamemode::BlackOnion => Vec::new(),
+			mode => vec![0; mode.splits()],
+		});
+		// Only filled in once a run starts or the comparison is changed
+		let comparison = &categories.comparison_cache;
+
+		let (mut total, mut comparison_total) = (0, 0);
+		let splits = scores
+			.iter()
+			.enumerate()
+			.map(|(split, &score)| {
+				let compare = comparison.get(split).copied().unwrap_or(0);
+				total += score;
+				comparison_total += compare;
+				SplitState {
+					split,
+					name: split_name(category.mode, split, true),
+					score,
+					total,
+					comparison: compare,
+					comparison_total,
+					delta: current_split
+						.is_some_and(|current| split <= current)
+						.then_some(total - comparison_total),
+					best: stats.gold_splits.get(split).copied().unwrap_or(0),
+				}
+			})
+			.collect();
+
+		LiveState {
+			category: category.name.clone(),
+			mode: category.mode,
+			hooked: splitter.last_frame_at.is_some(),
+			active: splitter.run.is_active(),
+			current_split,
+			current_split_name: current_split.map(|split| split_name(category.mode, split, true)),
+			score: splitter.run.score().unwrap_or(0),
+			comparison: categories.comparison().name(),
+			comparison_splits: comparison.clone(),
+			splits,
+			pb: stats.pb,
+			pb_splits: stats.pb_splits.clone(),
+			sum_of_best: stats.sum_of_best,
+			gold_splits: stats.gold_splits.clone(),
+			attempts: stats.attempts,
+		}
+	}
+}
+
+/// Handle to the HTTP server, which serves whatever was last published
+/// and pushes run events to WebSockets connected on `/events`
+pub struct ApiServer {
+	state: Arc<Mutex<Option<LiveState>>>,
+}
+
+impl ApiServer {
+	/// Serve JSON on `127.0.0.1:port` from a background thread, and send `events` to every WebSocket from another
+	pub fn start(port: u16, events: Receiver<RunEvent>) -> Result<Self, ZeroError> {
+		let server = Server::http(("127.0.0.1", port)).map_err(|err| ZeroError::ServerError(err.to_string()))?;
+		let state = Arc::new(Mutex::new(None));
+		let clients = Arc::new(Mutex::new(Vec::new()));
+
+		let (shared, listeners) = (state.clone(), clients.clone());
+		thread::Builder::new().name("http".to_owned()).spawn(move || {
+			for request in server.incoming_requests() {
+				if request.url() == "/events" {
+					accept(request, &listeners);
+				} else {
+					respond(request, &shared);
+				}
+			}
+		})?;
+		thread::Builder::new().name("events".to_owned()).spawn(move || {
+			for event in events {
+				broadcast(&event, &clients);
+			}
+		})?;
+		info!("HTTP API listening on http://127.0.0.1:{port}");
+		Ok(Self { state })
+	}
+
+	pub fn publish(&self, state: LiveState) {
+		*self.state.lock().unwrap() = Some(state);
+	}
+}
+
+/// The JSON for an endpoint, `None` if there's no such endpoint
+fn endpoint(path: &str, state: &LiveState) -> Option<Value> {
+	Some(match path {
+		"/" => json!({ "endpoints": ["/state", "/splits", "/comparison", "/pb", "/events", "/overlay"] }),
+		"/state" => json!({
+			"category": state.category,
+			"mode": state.mode,
+			"hooked": state.hooked,
+			"active": state.active,
+			"current_split": state.current_split,
+			"current_split_name": state.current_split_name,
+			"score": state.score,
+			"comparison": state.comparison,
+			"attempts": state.attempts,
+		}),
+		"/splits" => json!(state.splits),
+		"/comparison" => json!({ "name": state.comparison, "splits": state.comparison_splits }),
+		"/pb" => json!({
+			"pb": state.pb,
+			"pb_splits": state.pb_splits,
+			"sum_of_best": state.sum_of_best,
+			"gold_splits": state.gold_splits,
+		}),
+		_ => return None,
+	})
+}
+
+fn respond(request: Request, state: &Mutex<Option<LiveState>>) {
+	let path = request.url().split('?').next().unwrap_or_default().to_owned();
+	if path == "/overlay" && *request.method() == Method::Get {
+		let response = Response::from_string(overlay())
+			.with_header(Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap());
+		if let Err(err) = request.respond(response) {
+			debug!("Couldn't answer HTTP request for {path}: {err}");
+		}
+		return;
+	}
+
+	let (status, body) = if *request.method() != Method::Get {
+		(405, json!({ "error": "only GET is supported" }))
+	} else {
+		match state.lock().unwrap().as_ref() {
+			None => (503, json!({ "error": "starting up" })),
+			Some(state) => match endpoint(&path, state) {
+				Some(body) => (200, body),
+				None => (404, json!({ "error": format!("no endpoint {path}") })),
+			},
+		}
+	};
+
+	if let Err(err) = request.respond(json_response(status, body)) {
+		debug!("Couldn't answer HTTP request for {path}: {err}");
+	}
+}
+
+fn json_response(status: u16, body: Value) -> Response<Cursor<Vec<u8>>> {
+	Response::from_string(body.to_string())
+		.with_status_code(status)
+		.with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+		// Browser sources and dashboards are served from elsewhere
+		.with_header(Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap())
+}
+
+/// The overlay page with the colors of the window
+fn overlay() -> String {
+	let palette = [
+		("black", BLACK),
+		("white", WHITE),
+		("green", GREEN),
+		("dark-green", DARK_GREEN),
+		("darker-green", DARKER_GREEN),
+		("greenest", GREENEST),
+		("light-orange", LIGHT_ORANGE),
+		("dark-orange", DARK_ORANGE),
+		("darker-orange", DARKER_ORANGE),
+	]
+	.iter()
+	.map(|(name, color)| format!("--{name}: #{:02x}{:02x}{:02x};", color.r(), color.g(), color.b()))
+	.collect::<Vec<_>>()
+	.join(" ");
+	OVERLAY.replace("/* PALETTE */", &format!(":root {{ {palette} }}"))
+}
+
+/// Finish the WebSocket handshake for `/events`. The client gets every event from then on.
+fn accept(request: Request, clients: &Mutex<Vec<Client>>) {
+	let key = request
+		.headers()
+		.iter()
+		.find(|header| header.field.equiv("Sec-WebSocket-Key"))
+		.map(|header| derive_accept_key(header.value.as_bytes()));
+	let Some(accept) = key else {
+		let body = json!({ "error": "/events is a WebSocket" });
+		if let Err(err) = request.respond(json_response(400, body)) {
+			debug!("Couldn't answer HTTP request for /events: {err}");
+		}
+		return;
+	};
+
+	let response = Response::empty(101).with_header(Header::from_bytes("Sec-WebSocket-Accept", accept).unwrap());
+	let stream = request.upgrade("websocket", response);
+	let (tx, rx) = mpsc::sync_channel::<String>(CLIENT_QUEUE);
+	// Each client is written to from its own thread, so a slow one can't hold up the others or the server
+	let spawned = thread::Builder::new().name("websocket".to_owned()).spawn(move || {
+		let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+		for json in rx {
+			if let Err(err) = socket.send(Message::text(json)) {
+				debug!("WebSocket client left: {err}");
+				return;
+			}
+		}
+		// Dropped by broadcast
+		let _ = socket.close(None);
+		let _ = socket.flush();
+	});
+	match spawned {
+		Ok(_) => {
+			clients.lock().unwrap().push(tx);
+			debug!("WebSocket client connected");
+		}
+		Err(err) => warn!("Couldn't start WebSocket client thread: {err}"),
+	}
+}
+
+/// Queue an event for every client as JSON, dropping the ones that went away or fell too far behind
+fn broadcast(event: &RunEvent, clients: &Mutex<Vec<Client>>) {
+	let json = serde_json::to_string(event).unwrap();
+	clients
+		.lock()
+		.unwrap()
+		.retain(|client| match client.try_send(json.clone()) {
+			Ok(()) => true,
+			Err(TrySendError::Full(_)) => {
+				debug!("Dropping WebSocket client that isn't keeping up");
+				false
+			}
+			Err(TrySendError::Disconnected(_)) => false,
+		});
+}
+
+#[cfg(test)]
+impl LiveState {
+	/// A category with two splits and no run going
+	pub fn example() -> Self {
+		LiveState {
+			category: "default".to_owned(),
+			mode: Gamemode::GreenOrange,
+			hooked: true,
+			active: false,
+			current_split: None,
+			current_split_name: None,
+			score: 0,
+			comparison: "Personal Best".to_owned(),
+			comparison_splits: vec![10, 20],
+			splits: Vec::new(),
+			pb: 30,
+			pb_splits: vec![10, 20],
+			sum_of_best: 40,
+			gold_splits: vec![20, 20],
+			attempts: 2,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn endpoints() {
+		let state = LiveState::example();
+		assert_eq!(endpoint("/pb", &state).unwrap()["sum_of_best"], 40);
+		assert_eq!(endpoint("/state", &state).unwrap()["mode"], "GreenOrange");
+		assert_eq!(endpoint("/comparison", &state).unwrap()["splits"], json!([10, 20]));
+		assert!(endpoint("/splits/0", &state).is_none());
+	}
+
+	#[test]
+	fn drops_slow_clients() {
+		let (slow, _queue) = mpsc::sync_channel(CLIENT_QUEUE);
+		let (gone, _) = mpsc::sync_channel(CLIENT_QUEUE);
+		let clients = Mutex::new(vec![slow, gone]);
+		let event = RunEvent::HookStatus { connected: true };
+
+		broadcast(&event, &clients);
+		assert_eq!(clients.lock().unwrap().len(), 1);
+		for _ in 1..CLIENT_QUEUE {
+			broadcast(&event, &clients);
+		}
+		assert_eq!(clients.lock().unwrap().len(), 1);
+		broadcast(&event, &clients);
+		assert!(clients.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn overlay_has_palette() {
+		let page = overlay();
+		assert!(!page.contains("/* PALETTE */"));
+		assert!(page.contains("--green: #009479;"));
+		assert!(page.contains("--dark-orange: #ff8000;"));
+	}
+}
@@ -1,14 +1,18 @@
 use eframe::{
 	App, Frame,
-	egui::{Align, CentralPanel, Color32, ComboBox, Context, Id, Layout, Sense, Sides, Ui},
+	egui::{Align, CentralPanel, Color32, ComboBox, Context, DragValue, Id, Layout, RichText, Sense, Sides, Ui},
 };
+use log::error;
 
 use crate::{
-	Gamemode, Run, ZeroError, ZeroSplitter,
+	Category, DB_POLL_INTERVAL, Gamemode, HOOK_TIMEOUT, Run, ZeroError, ZeroSplitter,
+	comparison::{Comparison, ordinal_suffix},
 	config::{CONFIG, options_menu},
+	history::history_window,
+	split_name,
+	stats::stats_window,
 	theme::{DARK_GREEN, DARK_ORANGE, DARKER_GREEN, DARKER_ORANGE, GREEN, LIGHT_ORANGE},
 	ui::{category_maker_dialog, confirm_dialog},
-	vanilla_descriptive_split_names, vanilla_split_names,
 };
 
 pub struct Toggles {
@@ -17,6 +21,9 @@ pub struct Toggles {
 	pub show_gold_split: bool,
 	pub decorations: bool,
 	pub show_options_menu: bool,
+	pub show_stats: bool,
+	pub show_history: bool,
+	pub show_archived: bool,
 }
 
 impl Default for Toggles {
@@ -27,6 +34,9 @@ impl Default for Toggles {
 			show_gold_split: true,
 			decorations: true,
 			show_options_menu: false,
+			show_stats: false,
+			show_history: false,
+			show_archived: false,
 		}
 	}
 }
@@ -36,6 +46,17 @@ impl App for ZeroSplitter {
 		while let Ok(data) = self.data_source.try_recv() {
 			self.update_frame(data);
 		}
+		self.poll_saves();
+		self.check_hook();
+		self.publish();
+		if self.last_frame_at.is_some() {
+			// Nothing else repaints once the game is gone
+			ctx.request_repaint_after(HOOK_TIMEOUT);
+		}
+		if !self.saving.is_empty() || self.categories.is_loading() {
+			// Show what the database sends back without waiting for input
+			ctx.request_repaint_after(DB_POLL_INTERVAL);
+		}
 
 		// Detect gamemode change persist between frames
 		let prev_mode_id = Id::new("prev_mode");
@@ -71,7 +92,21 @@ impl App for ZeroSplitter {
 			}
 
 			if self.toggles.show_options_menu {
-				options_menu(ctx, &self.db, &mut self.toggles.show_options_menu);
+				options_menu(ctx, &self.db, &mut self.categories, &mut self.toggles.show_options_menu);
+			};
+
+			if self.toggles.show_stats {
+				stats_window(
+					ctx,
+					&self.db,
+					&mut self.categories,
+					&self.run,
+					&mut self.toggles.show_stats,
+				);
+			};
+
+			if self.toggles.show_history {
+				history_window(ctx, &self.db, &self.categories, &mut self.toggles.show_history);
 			};
 
 			ui.with_layout(Layout::top_down_justified(Align::Min), |ui| {
@@ -80,13 +115,27 @@ impl App for ZeroSplitter {
 						if ui.button("⚙").clicked() {
 							self.toggles.show_options_menu = true;
 						}
+						if ui
+							.button("STATS")
+							.on_hover_text("Statistics for this category")
+							.clicked()
+						{
+							self.toggles.show_stats = true;
+						}
+						if ui
+							.button("HISTORY")
+							.on_hover_text("Past runs of this category, with their tags and notes")
+							.clicked()
+						{
+							self.toggles.show_history = true;
+						}
 					});
 				});
 				ui.horizontal(|ui| {
 					ui.toggle_value(&mut self.toggles.relative_score, "RELATIVE")
 						.on_hover_text("Display relative score per split or running total of score");
 					ui.toggle_value(&mut self.toggles.show_gold_split, "BEST SPLITS")
-						.on_hover_text("Show your PB's splits or your best splits on the left");
+						.on_hover_text("Show your best splits or the comparison's splits on the left");
 					ui.toggle_value(&mut self.toggles.names, "NAMES")
 						.on_hover_text("Toggle descriptive or number names for WV splits");
 					if CONFIG.get().unwrap().decoration_button {
@@ -103,13 +152,29 @@ impl App for ZeroSplitter {
 				ui.horizontal(|ui| {
 					ui.label("Category: ");
 					{
-						let len = self.categories.len();
 						let mut cat_idx = self.categories.current;
-						ComboBox::from_label("")
-							.show_index(ui, &mut cat_idx, len, |i| &self.categories.index(i).unwrap().name);
-						if self.categories.current != cat_idx {
-							self.end_run();
-							self.categories.set_current(cat_idx, &self.db).unwrap();
+						let current = self.categories.current();
+						let combo = ComboBox::from_label("")
+							.selected_text(category_text(current))
+							.show_ui(ui, |ui| {
+								for idx in 0..self.categories.len() {
+									let category = self.categories.index(idx).unwrap();
+									if category.meta.archived && !self.toggles.show_archived && idx != cat_idx {
+										continue;
+									}
+									ui.selectable_value(&mut cat_idx, idx, category_text(category));
+								}
+								ui.separator();
+								ui.checkbox(&mut self.toggles.show_archived, "Show archived");
+							});
+						let details = category_details(current);
+						if !details.is_empty() {
+							combo.response.on_hover_text(details);
+						}
+						if self.categories.current != cat_idx
+							&& let Err(err) = self.select_category(cat_idx)
+						{
+							error!("Couldn't switch category: {err:?}");
 						}
 					}
 
@@ -123,6 +188,43 @@ impl App for ZeroSplitter {
 						self.waiting_for_rename = true;
 					}
 				});
+				ui.horizontal(|ui| {
+					ui.label("Compare: ");
+					let mut comparison = self.categories.comparison();
+					ComboBox::from_id_salt("comparison")
+						.selected_text(self.categories.comparison_name())
+						.show_ui(ui, |ui| {
+							for option in [
+								Comparison::PersonalBest,
+								Comparison::Average,
+								Comparison::Median,
+								Comparison::Latest,
+								Comparison::Percentile(75),
+							] {
+								let selected = matches!(
+									(comparison, option),
+									(Comparison::Percentile(_), Comparison::Percentile(_))
+								) || comparison == option;
+								if ui.selectable_label(selected, option.name()).clicked() && !selected {
+									comparison = option;
+								}
+							}
+							for goal in self.categories.goals() {
+								ui.selectable_value(
+									&mut comparison,
+									Comparison::Goal(goal.id),
+									format!("Goal: {}", goal.name),
+								);
+							}
+						});
+					if let Comparison::Percentile(p) = &mut comparison {
+						let suffix = ordinal_suffix(*p);
+						ui.add(DragValue::new(p).range(1..=99).suffix(suffix));
+					}
+					if comparison != self.categories.comparison() {
+						self.categories.set_comparison(comparison, &self.db);
+					}
+				});
 
 				if let Ok(data) = self.calculate_splits() {
 					self.display_splits(ui, data);
@@ -133,21 +235,36 @@ impl App for ZeroSplitter {
 					});
 				};
 
-				ui.label(format!(
-					"Personal Best: {}",
-					self.db.get_pb_run(&self.categories).map_or(0, |r| r.1)
-				));
-				ui.label(format!(
-					"Sum of Best: {}",
-					self.db.get_gold_splits(&self.categories).map_or(0, |s| s.iter().sum())
-				));
+				ui.label(format!("Personal Best: {}", self.categories.stats().pb));
+				ui.label(format!("Sum of Best: {}", self.categories.stats().sum_of_best));
+				if let (Ok(splits), Ok(current)) = (self.run.splits(), self.run.current_split())
+					&& let Some(split) = splits.get(current)
+				{
+					ui.label(format!("Multiplier: x{} (peak x{})", split.mult, split.peak_mult));
+					if split.peak_mult_p2 > 0 {
+						ui.label(format!(
+							"P2 Multiplier: x{} (peak x{})",
+							split.mult_p2, split.peak_mult_p2
+						));
+					}
+				}
+				if !self.saving.is_empty() {
+					ui.label("Saving run...");
+				} else if let Some(err) = &self.save_error {
+					ui.colored_label(DARK_ORANGE, format!("Couldn't save run: {err}"));
+				}
+				if self.categories.is_loading() {
+					ui.label("Loading...");
+				} else if let Some(err) = &self.categories.error {
+					ui.colored_label(DARK_ORANGE, format!("Couldn't load category: {err}"));
+				}
 			});
 		});
 
 		if self.waiting_for_category {
 			if let Ok(new_category) = self.dialog_rx.try_recv() {
 				if let Some(data) = new_category {
-					self.categories.push(data.textbox, data.mode, &self.db).unwrap();
+					self.categories.push(data.textbox, data.mode, &self.db);
 				}
 				self.waiting_for_category = false;
 			} else {
@@ -158,7 +275,7 @@ impl App for ZeroSplitter {
 		if self.waiting_for_rename {
 			if let Ok(rename_category) = self.dialog_rx.try_recv() {
 				if let Some(data) = rename_category {
-					self.categories.rename_current(&self.db, data.textbox).unwrap();
+					self.categories.rename_current(&self.db, data.textbox);
 				}
 				self.waiting_for_rename = false;
 			} else {
@@ -169,7 +286,7 @@ impl App for ZeroSplitter {
 		if self.waiting_for_confirm {
 			if let Ok(Some(confirmation)) = self.dialog_rx.try_recv() {
 				if confirmation.textbox == "Deleted" {
-					self.categories.delete_current(&self.db).unwrap();
+					self.categories.delete_current(&self.db);
 				}
 				self.waiting_for_confirm = false;
 			} else {
@@ -189,14 +306,46 @@ impl App for ZeroSplitter {
 		if let Run::Active { .. } = self.run {
 			self.save_splits();
 		}
+		self.finish_saves();
+	}
+}
+
+/// Category name in the category's color
+fn category_text(category: &Category) -> RichText {
+	let text = RichText::new(&category.name);
+	match category.meta.color {
+		Some(color) => text.color(Color32::from_rgb((color >> 16) as u8, (color >> 8) as u8, color as u8)),
+		None => text,
 	}
 }
 
+/// Ruleset, game version and notes of a category, one per line
+fn category_details(category: &Category) -> String {
+	let meta = &category.meta;
+	[
+		("Rules", &meta.ruleset),
+		("Game version", &meta.game_version),
+		("", &meta.notes),
+	]
+	.into_iter()
+	.filter(|(_, value)| !value.is_empty())
+	.map(|(label, value)| {
+		if label.is_empty() {
+			value.clone()
+		} else {
+			format!("{label}: {value}")
+		}
+	})
+	.collect::<Vec<_>>()
+	.join("\n")
+}
+
 impl ZeroSplitter {
 	fn calculate_splits(&self) -> Result<Vec<(i32, i32, i32)>, ZeroError> {
 		// relative split: score gained during one split
 		// absolute split: total score during one split
 		let raw_splits = self.run.scores()?;
+		let best_splits = &self.categories.stats().gold_splits;
 		let mut ret = Vec::new();
 		for (i, rel_split, abs_split) in raw_splits.iter().enumerate().map(|(i, &s)| {
 			(i, s, {
@@ -215,15 +364,14 @@ impl ZeroSplitter {
 			};
 			// Get relative/absolute gold split
 			// Gold split = high score of this split in any run
-			let best_splits = self.db.get_gold_splits(&self.categories);
-			let gold_split = match (best_splits, self.toggles.relative_score) {
-				(Ok(splits), true) => *splits.get(i).unwrap_or(&0),
-				(Ok(splits), false) => splits
+			let gold_split = if self.toggles.relative_score {
+				*best_splits.get(i).unwrap_or(&0)
+			} else {
+				best_splits
 					.iter()
 					.enumerate()
 					.take_while(|&(idx, _)| idx <= i)
-					.fold(0, |acc, (_, &s)| acc + s),
-				_ => 0,
+					.fold(0, |acc, (_, &s)| acc + s)
 			};
 			// Get relative/absolute split in the PB
 			// PB split = score of this split in the PB run
@@ -250,23 +398,10 @@ impl ZeroSplitter {
 		let current_split = self.run.current_split().unwrap_or(0);
 
 		for (n, &(gold_score, current_score, compare_score)) in split_data.iter().enumerate() {
-			// translate split number to stage/loop for GO
-			let stage_n = (n & 3) + 1;
-			let loop_n = (n >> 2) + 1;
 			Sides::new().show(
 				ui,
 				|left| {
-					match self.categories.current().mode {
-						Gamemode::GreenOrange => left.label(format!("{loop_n}-{stage_n}")),
-						Gamemode::WhiteVanilla => {
-							if self.toggles.names {
-								left.label(vanilla_descriptive_split_names(n))
-							} else {
-								left.label(vanilla_split_names(n))
-							}
-						}
-						Gamemode::BlackOnion => todo!(),
-					};
+					left.label(split_name(self.categories.current().mode, n, self.toggles.names));
 
 					if self.toggles.show_gold_split {
 						if gold_score > 0 {
@@ -0,0 +1,178 @@
+use std::{
+	fs::File,
+	io::{BufReader, BufWriter},
+	path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	CategoryMetadata, Gamemode, ZeroError,
+	database::{CURRENT_SCHEMA_VERSION, Database},
+};
+
+/// Version of the archive layout itself, bumped whenever the JSON structure changes
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// A full copy of the database in a form that doesn't depend on the SQLite file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Archive {
+	pub archive_version: u32,
+	pub schema_version: i32,
+	pub program_version: String,
+	pub categories: Vec<ArchivedCategory>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedCategory {
+	pub id: i64,
+	pub name: String,
+	pub mode: Gamemode,
+	#[serde(default)]
+	pub meta: CategoryMetadata,
+	pub runs: Vec<ArchivedRun>,
+	#[serde(default)]
+	pub goals: Vec<ArchivedGoal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedRun {
+	pub id: i64,
+	pub datetime: Option<String>,
+	pub imported: Option<bool>,
+	pub splits: Vec<ArchivedSplit>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedSplit {
+	pub id: i64,
+	pub split_num: i32,
+	pub score: i32,
+	pub hits: Option<i64>,
+	pub mult: Option<i64>,
+	#[serde(rename = "final")]
+	pub final_split: Option<bool>,
+	pub pattern_rank: Option<f64>,
+	pub dynamic_rank: Option<f64>,
+	#[serde(default)]
+	pub peak_mult: Option<i64>,
+	#[serde(default)]
+	pub mult_p2: Option<i64>,
+	#[serde(default)]
+	pub peak_mult_p2: Option<i64>,
+	#[serde(default)]
+	pub note: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedGoal {
+	pub name: String,
+	pub target: i32,
+	pub splits: Vec<i32>,
+}
+
+/// What a restore did to the database
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RestoreSummary {
+	pub categories_added: usize,
+	/// New categories that got a different name because one with the same name but another mode exists
+	pub categories_renamed: usize,
+	pub runs_added: usize,
+	pub runs_skipped: usize,
+	pub goals_added: usize,
+}
+
+/// Write the whole database to a JSON archive
+pub fn backup(db: &Database, path: impl AsRef<Path>) -> Result<(), ZeroError> {
+	let archive = db.dump_archive()?;
+	serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &archive)?;
+	Ok(())
+}
+
+/// Merge a JSON archive into the database.
+/// Categories are matched by name and mode and runs already in the database are skipped.
+pub fn restore(db: &Database, path: impl AsRef<Path>) -> Result<RestoreSummary, ZeroError> {
+	let archive: Archive = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+	check_archive(&archive)?;
+	Ok(db.restore_archive(&archive)?)
+}
+
+/// Refuse archives this version can't read, or with splits that don't exist in their mode
+fn check_archive(archive: &Archive) -> Result<(), ZeroError> {
+	if archive.archive_version > ARCHIVE_VERSION {
+		return Err(ZeroError::ArchiveTooNew(format!(
+			"archive version {} is newer than {ARCHIVE_VERSION}",
+			archive.archive_version
+		)));
+	}
+	if archive.schema_version > CURRENT_SCHEMA_VERSION {
+		return Err(ZeroError::ArchiveTooNew(format!(
+			"schema version {} is newer than {CURRENT_SCHEMA_VERSION}",
+			archive.schema_version
+		)));
+	}
+
+	for category in &archive.categories {
+		let splits = category.mode.splits();
+		for run in &category.runs {
+			if let Some(split) = run
+				.splits
+				.iter()
+				.find(|s| s.split_num < 0 || s.split_num as usize >= splits)
+			{
+				return Err(ZeroError::ImportError(format!(
+					"Category \"{}\": split {} is out of range for {:?} ({splits} splits)",
+					category.name, split.split_num, category.mode
+				)));
+			}
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn restore_merges_and_skips_duplicates() -> Result<(), ZeroError> {
+		let db = Database::init()?;
+		db.import_run(vec![10, 20, 30, 40], &"default".to_string())?;
+		let archive = db.dump_archive()?;
+
+		// Restoring into the same database changes nothing
+		let summary = db.restore_archive(&archive)?;
+		assert_eq!(summary.runs_added, 0);
+		assert_eq!(summary.runs_skipped, 1);
+
+		// Restoring into a fresh database recreates the runs
+		let other = Database::init()?;
+		other.insert_new_category("wv".to_owned(), Gamemode::WhiteVanilla)?;
+		let mut archive = archive;
+		archive.categories[0].name = "restored".to_owned();
+		let summary = other.restore_archive(&archive)?;
+		assert_eq!(summary.categories_added, 1);
+		assert_eq!(summary.runs_added, 1);
+		assert_eq!(other.get_split_records("restored")?.len(), 4);
+
+		// A category of another mode isn't merged into one with the same name, even when restored twice
+		archive.categories[0].name = "wv".to_owned();
+		let summary = other.restore_archive(&archive)?;
+		assert_eq!(summary.categories_added, 1);
+		assert_eq!(summary.categories_renamed, 1);
+		assert!(other.get_split_records("wv")?.is_empty());
+		assert_eq!(other.get_split_records("wv (GreenOrange)")?.len(), 4);
+		let summary = other.restore_archive(&archive)?;
+		assert_eq!(summary.categories_added, 0);
+		assert_eq!(summary.runs_skipped, 1);
+
+		archive.categories[0].runs[0].splits[0].split_num = 8;
+		assert!(matches!(check_archive(&archive), Err(ZeroError::ImportError(_))));
+
+		archive.schema_version = CURRENT_SCHEMA_VERSION + 1;
+		assert!(matches!(check_archive(&archive), Err(ZeroError::ArchiveTooNew(_))));
+		Ok(())
+	}
+}
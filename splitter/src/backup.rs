@@ -0,0 +1,177 @@
+use std::{
+	cmp::Reverse,
+	fs,
+	path::{Path, PathBuf},
+	sync::mpsc::Receiver,
+	time::{Duration, SystemTime},
+};
+
+use common::FrameData;
+use eframe::{
+	App, Frame,
+	egui::{CentralPanel, Context, RichText, ScrollArea},
+};
+use log::{error, info};
+
+use crate::{
+	ZeroError, ZeroSplitter,
+	config::CONFIG,
+	database::{DATABASE_FILE, Database},
+	open_database, profile,
+	theme::{DARK_ORANGE, GREEN},
+};
+
+/// Folder next to the database where backups are kept
+pub const BACKUP_DIR: &str = "backups";
+
+/// Prefix of the backups made on a schedule. Only these are rotated.
+const AUTO_PREFIX: &str = "auto";
+
+/// Make a scheduled backup if the newest one is older than the configured interval,
+/// then delete the oldest scheduled backups beyond the configured count (0 keeps them all).
+pub fn auto_backup(db: &Database) -> Result<Option<PathBuf>, ZeroError> {
+	let config = CONFIG.get().unwrap();
+	if config.backup_interval_hours == 0 {
+		return Ok(None);
+	}
+
+	let interval = Duration::from_secs(config.backup_interval_hours as u64 * 60 * 60);
+	let newest = list_backups()?
+		.into_iter()
+		.filter(|path| is_auto_backup(path))
+		.find_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+	let due = newest.is_none_or(|time| SystemTime::now().duration_since(time).unwrap_or_default() >= interval);
+
+	let made = if due {
+		let path = db.backup_into(&profile::path(BACKUP_DIR), AUTO_PREFIX)?;
+		info!("Made scheduled backup {}", path.display());
+		Some(path)
+	} else {
+		None
+	};
+
+	let kept = match config.backups_kept {
+		0 => usize::MAX,
+		kept => kept as usize,
+	};
+	for old in list_backups()?
+		.into_iter()
+		.filter(|path| is_auto_backup(path))
+		.skip(kept)
+	{
+		info!("Removing old backup {}", old.display());
+		fs::remove_file(old)?;
+	}
+
+	Ok(made)
+}
+
+/// All backups in the backup folder, newest first
+pub fn list_backups() -> Result<Vec<PathBuf>, ZeroError> {
+	let entries = match fs::read_dir(profile::path(BACKUP_DIR)) {
+		Ok(entries) => entries,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => return Err(err.into()),
+	};
+
+	let mut backups = entries
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| path.extension().is_some_and(|ext| ext == "db3"))
+		.filter_map(|path| Some((fs::metadata(&path).and_then(|m| m.modified()).ok()?, path)))
+		.collect::<Vec<_>>();
+	backups.sort_by_key(|(modified, _)| Reverse(*modified));
+	Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+fn is_auto_backup(path: &Path) -> bool {
+	path.file_name()
+		.is_some_and(|name| name.to_string_lossy().starts_with(AUTO_PREFIX))
+}
+
+/// Replace the database with a backup. The replaced database is kept next to it as `.broken`.
+pub fn restore_backup(backup: &Path) -> Result<(), ZeroError> {
+	let database = profile::path(DATABASE_FILE);
+	if database.exists() {
+		fs::copy(&database, database.with_extension("db3.broken"))?;
+	}
+	fs::copy(backup, &database)?;
+	info!("Restored database from {}", backup.display());
+	Ok(())
+}
+
+/// Shown instead of the splitter when the database can't be opened.
+/// Lets the user restore a backup, then carries on as the normal splitter.
+pub struct RecoveryApp {
+	error: String,
+	backups: Vec<PathBuf>,
+	selected: usize,
+	status: String,
+	data_source: Option<Receiver<FrameData>>,
+	splitter: Option<ZeroSplitter>,
+}
+
+impl RecoveryApp {
+	pub fn new(data_source: Receiver<FrameData>, error: ZeroError) -> Self {
+		error!("Failed to open database: {error:?}");
+		let (backups, status) = match list_backups() {
+			Ok(backups) => (backups, String::new()),
+			Err(err) => (Vec::new(), format!("Couldn't list backups: {err:?}")),
+		};
+		Self {
+			error: format!("{error:?}"),
+			backups,
+			selected: 0,
+			status,
+			data_source: Some(data_source),
+			splitter: None,
+		}
+	}
+
+	fn restore_selected(&mut self) {
+		let result = restore_backup(&self.backups[self.selected]).and_then(|_| open_database());
+		match result {
+			Ok((db, categories)) => {
+				self.splitter = Some(ZeroSplitter::new(self.data_source.take().unwrap(), db, categories))
+			}
+			Err(err) => self.status = format!("Restore failed: {err:?}"),
+		}
+	}
+}
+
+impl App for RecoveryApp {
+	fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+		if let Some(splitter) = &mut self.splitter {
+			splitter.update(ctx, frame);
+			return;
+		}
+
+		CentralPanel::default().show(ctx, |ui| {
+			ui.label(RichText::new("Couldn't open the database").color(GREEN).heading());
+			ui.colored_label(DARK_ORANGE, &self.error);
+			ui.separator();
+
+			if self.backups.is_empty() {
+				ui.label(format!("No backups found in {}", profile::path(BACKUP_DIR).display()));
+			} else {
+				ui.label("Restore a backup:");
+				ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+					for (idx, backup) in self.backups.iter().enumerate() {
+						let name = backup.file_name().unwrap_or_default().to_string_lossy();
+						ui.selectable_value(&mut self.selected, idx, name);
+					}
+				});
+				if ui.button("RESTORE").clicked() {
+					self.restore_selected();
+				}
+			}
+			ui.label(&self.status);
+		});
+	}
+
+	fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+		if let Some(splitter) = &mut self.splitter {
+			splitter.on_exit(gl);
+		}
+	}
+}
@@ -0,0 +1,234 @@
+use crate::{
+	Category, CategoryStats, ZeroError,
+	archive::{backup, restore},
+	csv_io::{ColumnMapping, export_csv, import_csv, read_headers},
+	daemon,
+	database::Database,
+	integrity::repair,
+	split_name, system,
+};
+
+const USAGE: &str = "\
+Usage: zerosplitter [COMMAND] [OPTIONS]
+
+Without a command, the window opens.
+
+Commands:
+  categories                                 List the categories
+  runs --category <NAME> [--tag <TAG>]       List the runs of a category, newest first
+  stats --category <NAME>                    PB, sum of best and pass rates of a category
+  export --category <NAME> --output <FILE>   Write every split of a category to a CSV file
+  import --category <NAME> --input <FILE>    Import runs from a CSV file with the exported column names
+  backup --output <FILE>                     Write the whole database to a JSON archive
+  restore --input <FILE>                     Merge a JSON archive into the database
+  check-db [--repair]                        Look for broken runs and splits, and fix what can be fixed
+  daemon [--category <NAME>]                 Split and save runs without the window, printing each split
+  help                                       Show this message
+
+Options for every command:
+  --data-dir <DIR>                           Folder holding the profiles
+  --profile <NAME>                           Profile to use
+";
+
+/// Flags that are on their own, every other flag is followed by a value
+const SWITCHES: [&str; 1] = ["--repair"];
+
+/// Run the command named on the command line, if there is one.
+/// `None` means there's no command and the window should open.
+pub fn run(args: &[String]) -> Option<Result<(), ZeroError>> {
+	let command = subcommand(args)?;
+	system::attach_console();
+	Some(run_command(command, args))
+}
+
+/// Whether the command line names a command, rather than opening the window
+pub fn has_command(args: &[String]) -> bool {
+	subcommand(args).is_some()
+}
+
+/// The value after a flag like `--profile`, if the flag is there
+pub fn flag(args: &[String], name: &str) -> Result<Option<String>, ZeroError> {
+	args.iter()
+		.position(|a| a == name)
+		.map(|idx| {
+			args.get(idx + 1)
+				.cloned()
+				.ok_or(ZeroError::ArgumentError(format!("{name} needs a value")))
+		})
+		.transpose()
+}
+
+/// The first argument that isn't a flag or a flag's value
+fn subcommand(args: &[String]) -> Option<&str> {
+	let mut args = args.iter().skip(1);
+	while let Some(arg) = args.next() {
+		if !arg.starts_with("--") {
+			return Some(arg);
+		}
+		if !SWITCHES.contains(&arg.as_str()) {
+			args.next();
+		}
+	}
+	None
+}
+
+fn required(args: &[String], name: &str) -> Result<String, ZeroError> {
+	flag(args, name)?.ok_or(ZeroError::ArgumentError(format!("{name} is required")))
+}
+
+fn run_command(command: &str, args: &[String]) -> Result<(), ZeroError> {
+	if command == "help" {
+		print!("{USAGE}");
+		return Ok(());
+	}
+	if command == "daemon" {
+		return daemon::run(args);
+	}
+
+	let db = Database::init()?;
+	match command {
+		"categories" => {
+			for category in db.get_categories()? {
+				let stats = CategoryStats::load(&db, &category)?;
+				let archived = if category.meta.archived { "  (archived)" } else { "" };
+				println!(
+					"{}  {:?}  {} runs  PB {}{archived}",
+					category.name, category.mode, stats.attempts, stats.pb
+				);
+			}
+		}
+		"runs" => {
+			let category = find_category(&db, &required(args, "--category")?)?;
+			for run in db.get_run_history(&category, flag(args, "--tag")?.as_deref())? {
+				let mut line = format!(
+					"{}  {}  {}  {} splits",
+					run.id,
+					run.datetime.as_deref().unwrap_or("unknown date"),
+					run.total(),
+					run.splits.len()
+				);
+				if run.imported == Some(true) {
+					line.push_str("  imported");
+				}
+				if !run.tags.is_empty() {
+					line.push_str(&format!("  [{}]", run.tags.join(", ")));
+				}
+				println!("{line}");
+			}
+		}
+		"stats" => print_stats(&db, &find_category(&db, &required(args, "--category")?)?)?,
+		"export" => {
+			let (name, path) = (required(args, "--category")?, required(args, "--output")?);
+			let rows = export_csv(&db, &name, &path)?;
+			println!("Exported {rows} splits to {path}");
+		}
+		"import" => {
+			let (name, path) = (required(args, "--category")?, required(args, "--input")?);
+			let mapping = ColumnMapping::from_headers(&read_headers(&path)?);
+			let runs = import_csv(&db, &name, &path, &mapping)?;
+			println!("Imported {runs} runs into {name}");
+		}
+		"backup" => {
+			let path = required(args, "--output")?;
+			backup(&db, &path)?;
+			println!("Backed up database to {path}");
+		}
+		"restore" => {
+			let summary = restore(&db, required(args, "--input")?)?;
+			println!(
+				"Restored {} runs and {} goals into {} new categories ({} renamed for a different mode), skipped {} duplicate runs",
+				summary.runs_added,
+				summary.goals_added,
+				summary.categories_added,
+				summary.categories_renamed,
+				summary.runs_skipped
+			);
+		}
+		"check-db" => {
+			let issues = db.check_integrity()?;
+			if issues.is_empty() {
+				println!("No issues found");
+				return Ok(());
+			}
+			for issue in &issues {
+				println!("{issue}");
+			}
+			if args.iter().any(|a| a == "--repair") {
+				let (backup, summary) = repair(&db)?;
+				println!(
+					"Removed {} runs and {} splits, fixed the end of {} runs and removed {} other rows. \
+					The old database is in {}",
+					summary.runs_removed,
+					summary.splits_removed,
+					summary.finals_cleared,
+					summary.other_rows_removed,
+					backup.display()
+				);
+			}
+		}
+		_ => {
+			return Err(ZeroError::ArgumentError(format!(
+				"Unknown command {command}, see zerosplitter help"
+			)));
+		}
+	}
+	Ok(())
+}
+
+fn find_category(db: &Database, name: &str) -> Result<Category, ZeroError> {
+	db.get_categories()?
+		.into_iter()
+		.find(|c| c.name == name)
+		.ok_or_else(|| ZeroError::ArgumentError(format!("No category named \"{name}\"")))
+}
+
+fn print_stats(db: &Database, category: &Category) -> Result<(), ZeroError> {
+	let stats = CategoryStats::load(db, category)?;
+	println!("{}  {:?}", category.name, category.mode);
+	println!("Attempts: {}", stats.attempts);
+	println!("PB: {}", stats.pb);
+	println!("Sum of best: {}", stats.sum_of_best);
+
+	let rates = db.get_pass_rates(category, None, None, None)?;
+	println!();
+	println!("Split    PB  Best  Reached  Passed  Pass %");
+	for split in 0..stats.gold_splits.len().max(stats.pb_splits.len()) {
+		let rate = rates.iter().find(|r| r.split_num == split);
+		println!(
+			"{:<5} {:>5} {:>5} {:>8} {:>7} {:>6}",
+			split_name(category.mode, split, false),
+			stats.pb_splits.get(split).map_or("-".to_owned(), |s| s.to_string()),
+			stats.gold_splits.get(split).map_or("-".to_owned(), |s| s.to_string()),
+			rate.map_or(0, |r| r.reached),
+			rate.map_or(0, |r| r.passed),
+			rate.map_or("-".to_owned(), |r| format!("{:.1}%", r.percentage())),
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(line: &str) -> Vec<String> {
+		line.split(' ').map(str::to_owned).collect()
+	}
+
+	#[test]
+	fn finds_subcommand() -> Result<(), ZeroError> {
+		assert_eq!(subcommand(&args("zerosplitter")), None);
+		assert_eq!(subcommand(&args("zerosplitter --profile practice")), None);
+		assert_eq!(
+			subcommand(&args("zerosplitter --profile runs stats --category runs")),
+			Some("stats")
+		);
+		assert_eq!(subcommand(&args("zerosplitter check-db --repair")), Some("check-db"));
+
+		let stats = args("zerosplitter stats --category WV --profile");
+		assert_eq!(flag(&stats, "--category")?, Some("WV".to_owned()));
+		assert_eq!(flag(&stats, "--tag")?, None);
+		assert!(flag(&stats, "--profile").is_err());
+		Ok(())
+	}
+}
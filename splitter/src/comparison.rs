@@ -0,0 +1,198 @@
+use crate::{Gamemode, database::SplitRecord};
+
+/// What the current run's splits are compared against
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+	#[default]
+	PersonalBest,
+	Average,
+	Median,
+	Latest,
+	/// Score per split that this percent of runs scored at or below
+	Percentile(u8),
+	/// A saved goal, by id
+	Goal(i64),
+}
+
+/// English ordinal suffix for a number, like the "rd" in 23rd
+pub fn ordinal_suffix(n: u8) -> &'static str {
+	match (n % 10, n % 100) {
+		(_, 11..=13) => "th",
+		(1, _) => "st",
+		(2, _) => "nd",
+		(3, _) => "rd",
+		_ => "th",
+	}
+}
+
+impl Comparison {
+	pub fn name(&self) -> String {
+		match self {
+			Comparison::PersonalBest => "Personal Best".to_owned(),
+			Comparison::Average => "Average".to_owned(),
+			Comparison::Median => "Median".to_owned(),
+			Comparison::Latest => "Latest Run".to_owned(),
+			Comparison::Percentile(p) => format!("{p}{} Percentile", ordinal_suffix(*p)),
+			Comparison::Goal(_) => "Goal".to_owned(),
+		}
+	}
+
+	/// Work out the comparison from the split history of a category.
+	/// Each split only counts runs that played it to the end. Not used for `PersonalBest` or `Goal`, which come
+	/// straight from the database.
+	pub fn calculate(&self, records: &[SplitRecord], mode: Gamemode) -> Vec<i32> {
+		if let Comparison::Latest = self {
+			let mut splits = vec![0; mode.splits()];
+			if let Some(latest) = records.iter().map(|r| r.run_id).max() {
+				for r in records.iter().filter(|r| r.run_id == latest) {
+					if let Some(split) = splits.get_mut(r.split_num as usize) {
+						*split = r.score;
+					}
+				}
+			}
+			return splits;
+		}
+
+		(0..mode.splits())
+			.map(|split| {
+				let mut scores = records
+					.iter()
+					.filter(|r| r.split_num as usize == split && r.score > 0 && r.is_whole(mode))
+					.map(|r| r.score)
+					.collect::<Vec<i32>>();
+				if scores.is_empty() {
+					return 0;
+				}
+				scores.sort();
+
+				match self {
+					Comparison::Average => (scores.iter().map(|&s| s as i64).sum::<i64>() / scores.len() as i64) as i32,
+					Comparison::Median if scores.len() % 2 == 0 => {
+						let mid = scores.len() / 2;
+						((scores[mid - 1] as i64 + scores[mid] as i64) / 2) as i32
+					}
+					Comparison::Median => scores[scores.len() / 2],
+					// nearest rank
+					Comparison::Percentile(p) => {
+						let rank = (*p as usize * scores.len()).div_ceil(100);
+						scores[rank.clamp(1, scores.len()) - 1]
+					}
+					Comparison::PersonalBest | Comparison::Latest | Comparison::Goal(_) => unreachable!(),
+				}
+			})
+			.collect()
+	}
+}
+
+/// A target score for a category, split up into a score for each split
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goal {
+	pub id: i64,
+	pub name: String,
+	pub target: i32,
+	pub splits: Vec<i32>,
+}
+
+/// What a goal's target is split up in proportion to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GoalBasis {
+	#[default]
+	GoldSplits,
+	Average,
+}
+
+/// Split `target` up in proportion to `basis`, so that the splits add up to exactly `target`.
+/// If there's nothing to go by, every split gets the same share.
+pub fn distribute_goal(target: i32, basis: &[i32]) -> Vec<i32> {
+	let mut basis = basis.iter().map(|&b| b.max(0) as i64).collect::<Vec<i64>>();
+	if basis.iter().sum::<i64>() == 0 {
+		basis.fill(1);
+	}
+	let basis_total = basis.iter().sum::<i64>();
+
+	// round the running total rather than each split so nothing is lost to rounding
+	let mut running_basis = 0;
+	let mut previous_total = 0;
+	basis
+		.iter()
+		.map(|&b| {
+			running_basis += b;
+			let total = ((target as i64 * running_basis * 2 + basis_total) / (basis_total * 2)) as i32;
+			let split = total - previous_total;
+			previous_total = total;
+			split
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn comparisons_from_history() {
+		// split 0 scored 100, 200, 300, 400 over four runs; only the last two reached split 1
+		let records = [
+			SplitRecord::new(1, 0, 100),
+			SplitRecord::new(2, 0, 200),
+			SplitRecord::new(3, 0, 300),
+			SplitRecord::new(3, 1, 50),
+			SplitRecord::new(4, 0, 400),
+			SplitRecord::new(4, 1, 70),
+		];
+		let history = |c: Comparison| c.calculate(&records, Gamemode::GreenOrange)[..2].to_vec();
+
+		assert_eq!(history(Comparison::Average), vec![250, 60]);
+		assert_eq!(history(Comparison::Median), vec![250, 60]);
+		assert_eq!(history(Comparison::Latest), vec![400, 70]);
+		assert_eq!(history(Comparison::Percentile(75)), vec![300, 70]);
+		assert_eq!(history(Comparison::Percentile(100)), vec![400, 70]);
+	}
+
+	#[test]
+	fn partial_splits_left_out() {
+		// run 2 was reset partway through split 1, run 3 ended on the last split
+		let partial = SplitRecord {
+			final_split: Some(true),
+			..SplitRecord::new(2, 1, 10)
+		};
+		let last = SplitRecord {
+			final_split: Some(true),
+			..SplitRecord::new(3, 7, 500)
+		};
+		let records = [
+			SplitRecord::new(1, 0, 100),
+			SplitRecord::new(1, 1, 90),
+			SplitRecord::new(2, 0, 200),
+			partial,
+			last,
+		];
+
+		let average = Comparison::Average.calculate(&records, Gamemode::GreenOrange);
+		assert_eq!(average[..2], [150, 90]);
+		assert_eq!(average[7], 500);
+		assert_eq!(Comparison::Median.calculate(&records, Gamemode::GreenOrange)[1], 90);
+		assert_eq!(
+			Comparison::Percentile(1).calculate(&records, Gamemode::GreenOrange)[1],
+			90
+		);
+	}
+
+	#[test]
+	fn percentile_names() {
+		assert_eq!(Comparison::Percentile(1).name(), "1st Percentile");
+		assert_eq!(Comparison::Percentile(2).name(), "2nd Percentile");
+		assert_eq!(Comparison::Percentile(3).name(), "3rd Percentile");
+		assert_eq!(Comparison::Percentile(11).name(), "11th Percentile");
+		assert_eq!(Comparison::Percentile(12).name(), "12th Percentile");
+		assert_eq!(Comparison::Percentile(21).name(), "21st Percentile");
+		assert_eq!(Comparison::Percentile(50).name(), "50th Percentile");
+	}
+
+	#[test]
+	fn goals_add_up_to_target() {
+		assert_eq!(distribute_goal(1000, &[100, 300, 0, 100]), vec![200, 600, 0, 200]);
+		assert_eq!(distribute_goal(100, &[1, 1, 1]).iter().sum::<i32>(), 100);
+		assert_eq!(distribute_goal(800, &[0; 8]), vec![100; 8]);
+	}
+}
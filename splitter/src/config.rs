@@ -1,22 +1,40 @@
 use std::{
 	fs::{File, OpenOptions, read_to_string},
 	io::{Read, Write},
+	path::PathBuf,
 	sync::OnceLock,
 };
 
 use crate::VERSION;
 
-use eframe::egui::{Context, Id, RichText, Separator, TextEdit, ViewportBuilder, ViewportId};
+use eframe::egui::{
+	ComboBox, Context, DragValue, Grid, Id, RichText, ScrollArea, Separator, TextEdit, Ui, ViewportBuilder,
+	ViewportCommand, ViewportId,
+};
+use log::{LevelFilter, info};
 use toml::{Table, Value};
 
-use crate::{ZeroError, theme::GREEN, update::check_for_updates};
+use crate::{
+	CategoryManager, CategoryMetadata, Gamemode, ZeroError,
+	archive::{backup, restore},
+	csv_io::{COLUMNS, ColumnMapping, export_csv, import_csv, read_headers},
+	importer::{ImportMode, ParsedRun, parse_run},
+	integrity::{Issue, repair},
+	logger, profile, split_name,
+	theme::{DARK_ORANGE, GREEN},
+	update::check_for_updates,
+	webhooks::Webhook,
+	worker::DbWorker,
+};
 
 pub static CONFIG: OnceLock<Config> = OnceLock::new();
 
-const CONFIG_PATH: &'static str = "config.toml";
+/// Name of the config file in the profile's folder
+const CONFIG_FILE: &str = "config.toml";
 
 pub fn load_config() -> Result<(), ZeroError> {
-	let config_str = match read_to_string(CONFIG_PATH) {
+	let config_path = profile::path(CONFIG_FILE);
+	let config_str = match read_to_string(&config_path) {
 		Ok(s) => s,
 		Err(e) => match e.kind() {
 			std::io::ErrorKind::NotFound => create_config()?,
@@ -25,7 +43,7 @@ pub fn load_config() -> Result<(), ZeroError> {
 	};
 	let table = config_str.parse::<Table>()?;
 
-	let mut writer = OpenOptions::new().append(true).open(CONFIG_PATH)?;
+	let mut writer = OpenOptions::new().append(true).open(&config_path)?;
 
 	let config = Config {
 		zoom_level: match table.get("zoom_level") {
@@ -52,6 +70,83 @@ pub fn load_config() -> Result<(), ZeroError> {
 			}
 			_ => return Err(ZeroError::ConfigError("check_for_updates".to_owned())),
 		},
+		backup_interval_hours: match table.get("backup_interval_hours") {
+			Some(Value::Integer(i)) if *i >= 0 => *i as u32,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/backup_interval_hours.toml"))?;
+				24
+			}
+			_ => return Err(ZeroError::ConfigError("backup_interval_hours".to_owned())),
+		},
+		backups_kept: match table.get("backups_kept") {
+			Some(Value::Integer(i)) if *i >= 0 => *i as u32,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/backups_kept.toml"))?;
+				7
+			}
+			_ => return Err(ZeroError::ConfigError("backups_kept".to_owned())),
+		},
+		log_level: match table.get("log_level") {
+			Some(Value::String(s)) => s.parse().map_err(|_| ZeroError::ConfigError("log_level".to_owned()))?,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/log_level.toml"))?;
+				LevelFilter::Info
+			}
+			_ => return Err(ZeroError::ConfigError("log_level".to_owned())),
+		},
+		http_server: match table.get("http_server") {
+			Some(Value::Boolean(b)) => *b,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/http_server.toml"))?;
+				false
+			}
+			_ => return Err(ZeroError::ConfigError("http_server".to_owned())),
+		},
+		http_port: match table.get("http_port") {
+			Some(Value::Integer(i)) if (1..=65535).contains(i) => *i as u16,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/http_port.toml"))?;
+				23889
+			}
+			_ => return Err(ZeroError::ConfigError("http_port".to_owned())),
+		},
+		livesplit_server: match table.get("livesplit_server") {
+			Some(Value::Boolean(b)) => *b,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/livesplit_server.toml"))?;
+				false
+			}
+			_ => return Err(ZeroError::ConfigError("livesplit_server".to_owned())),
+		},
+		livesplit_address: match table.get("livesplit_address") {
+			Some(Value::String(s)) => s.clone(),
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/livesplit_address.toml"))?;
+				"127.0.0.1:16834".to_owned()
+			}
+			_ => return Err(ZeroError::ConfigError("livesplit_address".to_owned())),
+		},
+		text_output_dir: match table.get("text_output_dir") {
+			Some(Value::String(s)) if s.is_empty() => None,
+			Some(Value::String(s)) => Some(PathBuf::from(s)),
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/text_output_dir.toml"))?;
+				None
+			}
+			_ => return Err(ZeroError::ConfigError("text_output_dir".to_owned())),
+		},
+		webhooks: match table.get("webhooks") {
+			Some(Value::Array(hooks)) => hooks
+				.iter()
+				.map(|hook| hook.clone().try_into::<Webhook>())
+				.collect::<Result<_, _>>()
+				.map_err(|err| ZeroError::ConfigError(format!("webhooks: {err}")))?,
+			None => {
+				writer.write_all(include_bytes!("../assets/config_sections/webhooks.toml"))?;
+				Vec::new()
+			}
+			_ => return Err(ZeroError::ConfigError("webhooks".to_owned())),
+		},
 	};
 
 	CONFIG.set(config).map_err(|_| ZeroError::StaticAlreadyInit)?;
@@ -59,7 +154,7 @@ pub fn load_config() -> Result<(), ZeroError> {
 }
 
 fn create_config() -> Result<String, ZeroError> {
-	let mut file = File::create_new(CONFIG_PATH)?;
+	let mut file = File::create_new(profile::path(CONFIG_FILE))?;
 	file.write_all(include_bytes!("../assets/config_sections/heading.toml"))?;
 	let mut ret = String::new();
 	file.read_to_string(&mut ret)?;
@@ -71,9 +166,19 @@ pub struct Config {
 	pub zoom_level: f32,
 	pub decoration_button: bool,
 	pub check_for_updates: bool,
+	pub backup_interval_hours: u32,
+	pub backups_kept: u32,
+	pub log_level: LevelFilter,
+	pub http_server: bool,
+	pub http_port: u16,
+	pub livesplit_server: bool,
+	pub livesplit_address: String,
+	/// Where to write text files for OBS, `None` to not write them
+	pub text_output_dir: Option<PathBuf>,
+	pub webhooks: Vec<Webhook>,
 }
 
-pub fn options_menu(ctx: &Context, db: &crate::database::Database, open: &mut bool) -> () {
+pub fn options_menu(ctx: &Context, db: &DbWorker, categories: &mut CategoryManager, open: &mut bool) -> () {
 	ctx.show_viewport_immediate(
 		ViewportId::from_hash_of("options_menu_viewport"),
 		ViewportBuilder::default().with_title("Options"),
@@ -105,18 +210,43 @@ pub fn options_menu(ctx: &Context, db: &crate::database::Database, open: &mut bo
 						);
 					});
 				};
+				// PROFILE
+				ui.horizontal(|ui| {
+					ui.label(RichText::new("Profile").color(GREEN).heading());
+					ui.add(Separator::default().horizontal())
+				});
+				profile_section(ui);
+
+				// CATEGORY
+				ui.horizontal(|ui| {
+					ui.label(RichText::new("Category").color(GREEN).heading());
+					ui.add(Separator::default().horizontal())
+				});
+				category_section(ui, db, categories);
+
 				// IMPORTER
 				ui.horizontal(|ui| {
 					ui.label(RichText::new("Importer").color(GREEN).heading());
 					ui.add(Separator::default().horizontal())
 				});
-				// Category name
-				let category_name_id = ui.label("Category name").id;
+				// Category
+				let category_name_id = ui.label("Category").id;
 				let mut category_name = ctx
 					.data(|data| data.get_temp::<String>(category_name_id))
-					.unwrap_or_default();
-				ui.add(TextEdit::singleline(&mut category_name));
+					.unwrap_or_else(|| categories.current().name.clone());
+				ComboBox::from_id_salt("importer_category")
+					.selected_text(&category_name)
+					.show_ui(ui, |ui| {
+						for idx in 0..categories.len() {
+							let name = &categories.index(idx).unwrap().name;
+							ui.selectable_value(&mut category_name, name.clone(), name);
+						}
+					});
 				ctx.data_mut(|data| data.insert_temp(category_name_id, category_name.clone()));
+				let gamemode = (0..categories.len())
+					.filter_map(|idx| categories.index(idx))
+					.find(|c| c.name == category_name)
+					.map(|c| c.mode);
 
 				// Split data
 				let splits_data_id = ui.label("Split scores").id;
@@ -124,25 +254,92 @@ pub fn options_menu(ctx: &Context, db: &crate::database::Database, open: &mut bo
 					.data(|data| data.get_temp::<String>(splits_data_id))
 					.unwrap_or_default();
 				ui.add(TextEdit::multiline(&mut run_string).hint_text("111, 222, 333, 444, 555, 666, 777, 888"));
-				ctx.data_mut(|data| data.insert_temp(splits_data_id, run_string.clone()));
 
-				if ui.button("IMPORT").clicked() {
-					let splits = run_string
-						.split(", ")
-						.map(|s| s.parse().unwrap_or_default())
-						.collect::<Vec<i32>>();
-					match db.import_run(splits.clone(), &category_name) {
-						Ok(_) => {
-							println!(
-								"Successfully imported run with {} splits and total score {}",
-								splits.len(),
-								splits.iter().sum::<i32>()
+				let import_mode_id = Id::new("import_mode");
+				let mut import_mode = ctx
+					.data(|data| data.get_temp::<ImportMode>(import_mode_id))
+					.unwrap_or_default();
+				ui.horizontal(|ui| {
+					ui.label("Scores are");
+					ui.selectable_value(&mut import_mode, ImportMode::Relative, "Per split");
+					ui.selectable_value(&mut import_mode, ImportMode::RunningTotal, "Running total");
+				});
+				ctx.data_mut(|data| data.insert_temp(import_mode_id, import_mode));
+
+				let import_status_id = Id::new("import_status");
+				match gamemode.map(|mode| (mode, parse_run(&run_string, import_mode, mode))) {
+					_ if run_string.trim().is_empty() => (),
+					None => {
+						ui.colored_label(DARK_ORANGE, "Choose a category to import into");
+					}
+					Some((_, Err(ZeroError::ImportError(msg)))) => {
+						ui.colored_label(DARK_ORANGE, msg);
+					}
+					Some((_, Err(err))) => {
+						ui.colored_label(DARK_ORANGE, format!("{err:?}"));
+					}
+					Some((mode, Ok(run))) => {
+						if run.looks_like_totals() {
+							ui.label(
+								"Every score is higher than the one before. If these are running totals, pick Running total.",
 							);
-							run_string.clear();
 						}
-						Err(err) => println!("Import failed: {err}"),
-					};
-				};
+						import_preview(ui, mode, &run);
+
+						if ui.button("IMPORT").clicked() {
+							let (splits, name) = (run.splits.clone(), category_name.clone());
+							let status = match db
+								.call(move |db| Ok(db.import_run(splits, &name)?))
+								.inspect(|_| categories.refresh_stats(db))
+							{
+								Ok(_) => {
+									run_string.clear();
+									format!(
+										"Imported run with {} splits and total score {}",
+										run.splits.len(),
+										run.splits.iter().sum::<i32>()
+									)
+								}
+								Err(err) => format!("Import failed: {err:?}"),
+							};
+							info!("{status}");
+							ctx.data_mut(|data| data.insert_temp(import_status_id, status));
+						};
+					}
+				}
+				ctx.data_mut(|data| data.insert_temp(splits_data_id, run_string.clone()));
+				ui.label(
+					ctx.data(|data| data.get_temp::<String>(import_status_id))
+						.unwrap_or_default(),
+				);
+
+				// CSV
+				ui.horizontal(|ui| {
+					ui.label(RichText::new("CSV").color(GREEN).heading());
+					ui.add(Separator::default().horizontal())
+				});
+				csv_section(ui, db, categories, &category_name);
+
+				// BACKUP
+				ui.horizontal(|ui| {
+					ui.label(RichText::new("Backup").color(GREEN).heading());
+					ui.add(Separator::default().horizontal())
+				});
+				backup_section(ui, db, categories);
+
+				// INTEGRITY
+				ui.horizontal(|ui| {
+					ui.label(RichText::new("Integrity").color(GREEN).heading());
+					ui.add(Separator::default().horizontal())
+				});
+				integrity_section(ui, db, categories);
+
+				// LOG
+				ui.horizontal(|ui| {
+					ui.label(RichText::new("Log").color(GREEN).heading());
+					ui.add(Separator::default().horizontal())
+				});
+				log_section(ui);
 			});
 
 			if ctx.input(|i| i.viewport().close_requested()) {
@@ -151,3 +348,364 @@ pub fn options_menu(ctx: &Context, db: &crate::database::Database, open: &mut bo
 		},
 	);
 }
+
+/// Edit the current category's metadata
+fn category_section(ui: &mut Ui, db: &DbWorker, categories: &mut CategoryManager) {
+	let ctx = ui.ctx().clone();
+	let category = categories.current();
+	let meta_id = Id::new(("category_meta", category.id));
+	let status_id = Id::new("category_status");
+
+	let mut meta = ctx
+		.data(|data| data.get_temp::<CategoryMetadata>(meta_id))
+		.unwrap_or_else(|| category.meta.clone());
+	ui.label(format!("Editing {}", category.name));
+
+	Grid::new("category_meta").num_columns(2).show(ui, |ui| {
+		ui.label("Rules");
+		ui.add(TextEdit::singleline(&mut meta.ruleset).hint_text("e.g. no continues"));
+		ui.end_row();
+		ui.label("Game version");
+		ui.add(TextEdit::singleline(&mut meta.game_version));
+		ui.end_row();
+		ui.label("Notes");
+		ui.add(TextEdit::multiline(&mut meta.notes).desired_rows(2));
+		ui.end_row();
+		ui.label("Color");
+		ui.horizontal(|ui| {
+			let mut colored = meta.color.is_some();
+			ui.checkbox(&mut colored, "");
+			let color = meta.color.unwrap_or(0xffffff);
+			let mut rgb = [(color >> 16) as u8, (color >> 8) as u8, color as u8];
+			ui.add_enabled_ui(colored, |ui| ui.color_edit_button_srgb(&mut rgb));
+			meta.color = colored.then_some(u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]]));
+		});
+		ui.end_row();
+		ui.label("Sort order");
+		ui.add(DragValue::new(&mut meta.sort_order))
+			.on_hover_text("Categories are listed from lowest to highest");
+		ui.end_row();
+		ui.label("Archived");
+		ui.checkbox(&mut meta.archived, "Hide from the category list");
+		ui.end_row();
+	});
+
+	if ui
+		.add_enabled(meta != category.meta, eframe::egui::Button::new("SAVE"))
+		.clicked()
+	{
+		let status = match categories.set_metadata(meta.clone(), db) {
+			Ok(_) => "Saved".to_owned(),
+			Err(err) => format!("Couldn't save: {err:?}"),
+		};
+		ctx.data_mut(|data| data.insert_temp(status_id, status));
+	}
+	ui.label(ctx.data(|data| data.get_temp::<String>(status_id)).unwrap_or_default());
+
+	ctx.data_mut(|data| data.insert_temp(meta_id, meta));
+}
+
+/// Switch to another profile, which restarts the program, or make a new one
+fn profile_section(ui: &mut Ui) {
+	let ctx = ui.ctx().clone();
+	let selected_id = Id::new("profile_selected");
+	let new_name_id = Id::new("profile_new_name");
+	let status_id = Id::new("profile_status");
+	let current = profile::current();
+
+	ui.label(format!("Using {} in {}", current.name, current.dir().display()));
+
+	let profiles = profile::list_profiles().unwrap_or_else(|_| vec![current.name.clone()]);
+	let mut selected = ctx
+		.data(|data| data.get_temp::<String>(selected_id))
+		.unwrap_or_else(|| current.name.clone());
+	ui.horizontal(|ui| {
+		ComboBox::from_id_salt("profile")
+			.selected_text(&selected)
+			.show_ui(ui, |ui| {
+				for name in &profiles {
+					ui.selectable_value(&mut selected, name.clone(), name);
+				}
+			});
+		if ui
+			.add_enabled(selected != current.name, eframe::egui::Button::new("SWITCH"))
+			.on_hover_text("Restarts ZeroSplitter with this profile")
+			.clicked()
+		{
+			match profile::relaunch_with(&selected) {
+				// Closing the main window saves the current run
+				Ok(_) => ctx.send_viewport_cmd_to(ViewportId::ROOT, ViewportCommand::Close),
+				Err(err) => ctx.data_mut(|data| data.insert_temp(status_id, format!("Couldn't switch: {err:?}"))),
+			}
+		}
+	});
+
+	let mut new_name = ctx
+		.data(|data| data.get_temp::<String>(new_name_id))
+		.unwrap_or_default();
+	ui.horizontal(|ui| {
+		ui.add(
+			TextEdit::singleline(&mut new_name)
+				.hint_text("New profile name")
+				.desired_width(120.0),
+		);
+		if ui.button("CREATE").clicked() {
+			let status = match profile::create_profile(&new_name) {
+				Ok(_) => {
+					selected = new_name.clone();
+					format!("Created profile {}", std::mem::take(&mut new_name))
+				}
+				Err(ZeroError::ProfileError(msg)) => msg,
+				Err(err) => format!("Couldn't create profile: {err:?}"),
+			};
+			ctx.data_mut(|data| data.insert_temp(status_id, status));
+		}
+	});
+	ui.label(ctx.data(|data| data.get_temp::<String>(status_id)).unwrap_or_default());
+
+	ctx.data_mut(|data| {
+		data.insert_temp(selected_id, selected);
+		data.insert_temp(new_name_id, new_name);
+	});
+}
+
+/// Table of the splits about to be imported, next to the names of the category's splits
+fn import_preview(ui: &mut Ui, mode: Gamemode, run: &ParsedRun) {
+	Grid::new("import_preview").striped(true).show(ui, |ui| {
+		ui.colored_label(GREEN, "Split");
+		ui.colored_label(GREEN, "Score");
+		ui.colored_label(GREEN, "Total");
+		ui.end_row();
+		for (n, (split, total)) in run.splits.iter().zip(run.totals()).enumerate() {
+			ui.label(split_name(mode, n, true));
+			ui.label(split.to_string());
+			ui.label(total.to_string());
+			ui.end_row();
+		}
+	});
+}
+
+/// JSON backup of the whole database, and restoring one by merging it in
+fn backup_section(ui: &mut Ui, db: &DbWorker, categories: &mut CategoryManager) {
+	let ctx = ui.ctx().clone();
+	let path_id = ui.label("Backup file").id;
+	let status_id = Id::new("backup_status");
+
+	let mut path = ctx
+		.data(|data| data.get_temp::<String>(path_id))
+		.unwrap_or_else(|| "zerosplitter_backup.json".to_owned());
+	ui.add(TextEdit::singleline(&mut path));
+	ctx.data_mut(|data| data.insert_temp(path_id, path.clone()));
+
+	ui.horizontal(|ui| {
+		if ui.button("BACK UP").clicked() {
+			let backup_path = path.clone();
+			let status = match db.call(move |db| backup(db, backup_path)) {
+				Ok(_) => format!("Backed up database to {path}"),
+				Err(err) => format!("Backup failed: {err:?}"),
+			};
+			ctx.data_mut(|data| data.insert_temp(status_id, status));
+		}
+		if ui.button("RESTORE").clicked() {
+			let restore_path = path.clone();
+			let status = match db
+				.call(move |db| restore(db, restore_path))
+				.inspect(|_| categories.load(db))
+			{
+				Ok(summary) => format!(
+					"Restored {} runs and {} goals into {} new categories ({} renamed for a different mode), skipped {} duplicate runs",
+					summary.runs_added,
+					summary.goals_added,
+					summary.categories_added,
+					summary.categories_renamed,
+					summary.runs_skipped
+				),
+				Err(err) => format!("Restore failed: {err:?}"),
+			};
+			ctx.data_mut(|data| data.insert_temp(status_id, status));
+		}
+	});
+
+	ui.label(ctx.data(|data| data.get_temp::<String>(status_id)).unwrap_or_default());
+}
+
+/// Look for broken runs and splits, and repair the ones that can be fixed safely
+fn integrity_section(ui: &mut Ui, db: &DbWorker, categories: &mut CategoryManager) {
+	let ctx = ui.ctx().clone();
+	let issues_id = Id::new("integrity_issues");
+	let status_id = Id::new("integrity_status");
+	let issues = ctx.data(|data| data.get_temp::<Vec<Issue>>(issues_id));
+
+	ui.horizontal(|ui| {
+		if ui
+			.button("CHECK")
+			.on_hover_text("Look for broken runs and splits without changing anything")
+			.clicked()
+		{
+			match db.call(|db| Ok(db.check_integrity()?)) {
+				Ok(issues) => ctx.data_mut(|data| {
+					data.insert_temp(issues_id, issues);
+					data.remove::<String>(status_id);
+				}),
+				Err(err) => ctx.data_mut(|data| data.insert_temp(status_id, format!("Check failed: {err:?}"))),
+			}
+		}
+		let repairable = issues
+			.as_ref()
+			.is_some_and(|issues| issues.iter().any(|issue| issue.kind.repairable()));
+		if ui
+			.add_enabled(repairable, eframe::egui::Button::new("REPAIR"))
+			.on_hover_text("Back up the database, then fix the issues that can be fixed")
+			.clicked()
+		{
+			let status = match db
+				.call(|db| Ok((repair(db)?, db.check_integrity()?)))
+				.inspect(|_| categories.load(db))
+			{
+				Ok(((backup, summary), issues)) => {
+					ctx.data_mut(|data| data.insert_temp(issues_id, issues));
+					format!(
+						"Removed {} runs and {} splits, fixed the end of {} runs and removed {} other rows. \
+						The old database is in {}",
+						summary.runs_removed,
+						summary.splits_removed,
+						summary.finals_cleared,
+						summary.other_rows_removed,
+						backup.display()
+					)
+				}
+				Err(err) => format!("Repair failed: {err:?}"),
+			};
+			ctx.data_mut(|data| data.insert_temp(status_id, status));
+		}
+	});
+
+	match issues {
+		Some(issues) if issues.is_empty() => {
+			ui.label("No issues found");
+		}
+		Some(issues) => {
+			for issue in issues {
+				if issue.kind.repairable() {
+					ui.label(issue.to_string());
+				} else {
+					ui.colored_label(DARK_ORANGE, issue.to_string());
+				}
+			}
+		}
+		None => {}
+	}
+	ui.label(ctx.data(|data| data.get_temp::<String>(status_id)).unwrap_or_default());
+}
+
+/// The last lines of the log, to copy into a bug report
+fn log_section(ui: &mut Ui) {
+	if let Some(path) = logger::log_path() {
+		ui.label(format!("Written to {}", path.display()));
+	}
+
+	let lines = logger::recent_lines();
+	ui.horizontal(|ui| {
+		if ui.button("COPY").on_hover_text("Copy the lines below").clicked() {
+			ui.ctx().copy_text(lines.join("\n"));
+		}
+		let mut level = log::max_level();
+		ComboBox::from_id_salt("log_level")
+			.selected_text(level.as_str())
+			.show_ui(ui, |ui| {
+				for option in [
+					LevelFilter::Error,
+					LevelFilter::Warn,
+					LevelFilter::Info,
+					LevelFilter::Debug,
+					LevelFilter::Trace,
+				] {
+					ui.selectable_value(&mut level, option, option.as_str());
+				}
+			})
+			.response
+			.on_hover_text("Until ZeroSplitter is closed. Set log_level in config.toml to keep it.");
+		if level != log::max_level() {
+			logger::set_level(level);
+		}
+	});
+
+	ScrollArea::vertical()
+		.max_height(200.0)
+		.stick_to_bottom(true)
+		.show(ui, |ui| {
+			for line in &lines {
+				ui.monospace(line);
+			}
+		});
+}
+
+/// CSV export and import of the category chosen in the importer
+fn csv_section(ui: &mut Ui, db: &DbWorker, categories: &mut CategoryManager, category_name: &str) {
+	let ctx = ui.ctx().clone();
+	let path_id = ui.label("CSV file").id;
+	let headers_id = Id::new("csv_headers");
+	let mapping_id = Id::new("csv_mapping");
+	let status_id = Id::new("csv_status");
+
+	let mut path = ctx
+		.data(|data| data.get_temp::<String>(path_id))
+		.unwrap_or_else(|| "splits.csv".to_owned());
+	ui.add(TextEdit::singleline(&mut path));
+	ctx.data_mut(|data| data.insert_temp(path_id, path.clone()));
+
+	ui.horizontal(|ui| {
+		if ui.button("EXPORT").clicked() {
+			let (name, export_path) = (category_name.to_owned(), path.clone());
+			let status = match db.call(move |db| export_csv(db, &name, export_path)) {
+				Ok(rows) => format!("Exported {rows} splits to {path}"),
+				Err(err) => format!("Export failed: {err:?}"),
+			};
+			ctx.data_mut(|data| data.insert_temp(status_id, status));
+		}
+		if ui.button("LOAD").clicked() {
+			match read_headers(&path) {
+				Ok(headers) => ctx.data_mut(|data| {
+					data.insert_temp(mapping_id, ColumnMapping::from_headers(&headers));
+					data.insert_temp(headers_id, headers);
+					data.remove_temp::<String>(status_id);
+				}),
+				Err(err) => ctx.data_mut(|data| data.insert_temp(status_id, format!("Load failed: {err:?}"))),
+			}
+		}
+	});
+
+	// Column mapping, shown once a file has been loaded
+	if let Some(headers) = ctx.data(|data| data.get_temp::<Vec<String>>(headers_id)) {
+		let mut mapping = ctx
+			.data(|data| data.get_temp::<ColumnMapping>(mapping_id))
+			.unwrap_or_default();
+		for (field, name) in COLUMNS.iter().enumerate() {
+			let selected = mapping.columns[field].map_or("(none)", |col| headers[col].as_str());
+			ComboBox::from_label(*name).selected_text(selected).show_ui(ui, |ui| {
+				ui.selectable_value(&mut mapping.columns[field], None, "(none)");
+				for (col, header) in headers.iter().enumerate() {
+					ui.selectable_value(&mut mapping.columns[field], Some(col), header);
+				}
+			});
+		}
+		ctx.data_mut(|data| data.insert_temp(mapping_id, mapping.clone()));
+
+		if ui.button("IMPORT CSV").clicked() {
+			let (name, import_path, mapping) = (category_name.to_owned(), path.clone(), mapping.clone());
+			let status = match db
+				.call(move |db| import_csv(db, &name, import_path, &mapping))
+				.inspect(|_| categories.refresh_stats(db))
+			{
+				Ok(runs) => {
+					ctx.data_mut(|data| data.remove_temp::<Vec<String>>(headers_id));
+					format!("Imported {runs} runs into {category_name}")
+				}
+				Err(err) => format!("Import failed: {err:?}"),
+			};
+			ctx.data_mut(|data| data.insert_temp(status_id, status));
+		}
+	}
+
+	ui.label(ctx.data(|data| data.get_temp::<String>(status_id)).unwrap_or_default());
+}
@@ -0,0 +1,299 @@
+use std::{
+	collections::HashSet,
+	fs::File,
+	io::{Read, Write},
+	path::Path,
+};
+
+use crate::{
+	Gamemode, ZeroError,
+	database::{Database, SplitRecord},
+};
+
+/// Column names in the order they are exported
+pub const COLUMNS: [&str; 12] = [
+	"run_id",
+	"datetime",
+	"split_num",
+	"score",
+	"mult",
+	"pattern_rank",
+	"dynamic_rank",
+	"final",
+	"imported",
+	"peak_mult",
+	"mult_p2",
+	"peak_mult_p2",
+];
+
+const RUN_ID: usize = 0;
+const DATETIME: usize = 1;
+const SPLIT_NUM: usize = 2;
+const SCORE: usize = 3;
+const MULT: usize = 4;
+const PATTERN_RANK: usize = 5;
+const DYNAMIC_RANK: usize = 6;
+const FINAL: usize = 7;
+const IMPORTED: usize = 8;
+const PEAK_MULT: usize = 9;
+const MULT_P2: usize = 10;
+const PEAK_MULT_P2: usize = 11;
+
+/// Columns that have to be mapped for an import to be possible
+const REQUIRED: [usize; 3] = [RUN_ID, SPLIT_NUM, SCORE];
+
+/// Which column of the CSV file feeds each of the fields in `COLUMNS`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnMapping {
+	pub columns: [Option<usize>; COLUMNS.len()],
+}
+
+impl ColumnMapping {
+	/// Guess the mapping by matching header names against `COLUMNS`
+	pub fn from_headers(headers: &[String]) -> Self {
+		let mut mapping = Self::default();
+		for (field, name) in COLUMNS.iter().enumerate() {
+			mapping.columns[field] = headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+		}
+		mapping
+	}
+}
+
+/// Write every split of a category to a CSV file. Returns the number of rows written.
+pub fn export_csv(db: &Database, category_name: &str, path: impl AsRef<Path>) -> Result<usize, ZeroError> {
+	find_mode(db, category_name)?;
+	let records = db.get_split_records(category_name)?;
+	write_records(&records, File::create(path)?)?;
+	Ok(records.len())
+}
+
+/// Read the header row of a CSV file
+pub fn read_headers(path: impl AsRef<Path>) -> Result<Vec<String>, ZeroError> {
+	let mut reader = csv::Reader::from_path(path)?;
+	Ok(reader.headers()?.iter().map(str::to_owned).collect())
+}
+
+/// Validate a CSV file and import its runs into a category. Nothing is written if any row is invalid.
+/// Returns the number of runs imported.
+pub fn import_csv(
+	db: &Database,
+	category_name: &str,
+	path: impl AsRef<Path>,
+	mapping: &ColumnMapping,
+) -> Result<usize, ZeroError> {
+	let mode = find_mode(db, category_name)?;
+	let records = parse_records(File::open(path)?, mapping, mode)?;
+	Ok(db.import_split_records(category_name, &records)?)
+}
+
+fn find_mode(db: &Database, category_name: &str) -> Result<Gamemode, ZeroError> {
+	db.get_categories()?
+		.into_iter()
+		.find(|c| c.name == category_name)
+		.map(|c| c.mode)
+		.ok_or_else(|| ZeroError::ImportError(format!("No category named \"{category_name}\"")))
+}
+
+pub fn write_records<W: Write>(records: &[SplitRecord], writer: W) -> Result<(), ZeroError> {
+	let mut writer = csv::Writer::from_writer(writer);
+	writer.write_record(COLUMNS)?;
+	for r in records {
+		writer.write_record([
+			r.run_id.to_string(),
+			r.datetime.clone().unwrap_or_default(),
+			r.split_num.to_string(),
+			r.score.to_string(),
+			r.mult.map(|m| m.to_string()).unwrap_or_default(),
+			r.pattern_rank.map(|p| p.to_string()).unwrap_or_default(),
+			r.dynamic_rank.map(|d| d.to_string()).unwrap_or_default(),
+			r.final_split.map(|f| f.to_string()).unwrap_or_default(),
+			r.imported.map(|i| i.to_string()).unwrap_or_default(),
+			r.peak_mult.map(|m| m.to_string()).unwrap_or_default(),
+			r.mult_p2.map(|m| m.to_string()).unwrap_or_default(),
+			r.peak_mult_p2.map(|m| m.to_string()).unwrap_or_default(),
+		])?;
+	}
+	writer.flush()?;
+	Ok(())
+}
+
+/// Parse and validate the rows of a CSV file. Records are returned sorted by run and split.
+pub fn parse_records<R: Read>(
+	reader: R,
+	mapping: &ColumnMapping,
+	mode: Gamemode,
+) -> Result<Vec<SplitRecord>, ZeroError> {
+	for field in REQUIRED {
+		if mapping.columns[field].is_none() {
+			return Err(ZeroError::ImportError(format!(
+				"Column \"{}\" is not mapped",
+				COLUMNS[field]
+			)));
+		}
+	}
+
+	let mut reader = csv::Reader::from_reader(reader);
+	let mut records = Vec::new();
+	let mut seen = HashSet::new();
+	let mut finals = HashSet::new();
+	for (idx, row) in reader.records().enumerate() {
+		let row = row?;
+		// header is line 1
+		let line = idx + 2;
+		let get = |field: usize| {
+			mapping.columns[field]
+				.and_then(|col| row.get(col))
+				.map(str::trim)
+				.filter(|s| !s.is_empty())
+		};
+		let record = SplitRecord {
+			run_id: parse_required(get(RUN_ID), RUN_ID, line)?,
+			datetime: get(DATETIME).map(str::to_owned),
+			split_num: parse_required(get(SPLIT_NUM), SPLIT_NUM, line)?,
+			score: parse_required(get(SCORE), SCORE, line)?,
+			mult: parse_optional(get(MULT), MULT, line)?,
+			pattern_rank: parse_optional(get(PATTERN_RANK), PATTERN_RANK, line)?,
+			dynamic_rank: parse_optional(get(DYNAMIC_RANK), DYNAMIC_RANK, line)?,
+			final_split: get(FINAL).map(|s| parse_bool(s, FINAL, line)).transpose()?,
+			imported: get(IMPORTED).map(|s| parse_bool(s, IMPORTED, line)).transpose()?,
+			peak_mult: parse_optional(get(PEAK_MULT), PEAK_MULT, line)?,
+			mult_p2: parse_optional(get(MULT_P2), MULT_P2, line)?,
+			peak_mult_p2: parse_optional(get(PEAK_MULT_P2), PEAK_MULT_P2, line)?,
+		};
+
+		if record.split_num < 0 || record.split_num as usize >= mode.splits() {
+			return Err(ZeroError::ImportError(format!(
+				"Line {line}: split {} is out of range for {mode:?} ({} splits)",
+				record.split_num,
+				mode.splits()
+			)));
+		}
+		if record.score < 0 {
+			return Err(ZeroError::ImportError(format!(
+				"Line {line}: score {} is negative",
+				record.score
+			)));
+		}
+		if !seen.insert((record.run_id, record.split_num)) {
+			return Err(ZeroError::ImportError(format!(
+				"Line {line}: run {} has split {} more than once",
+				record.run_id, record.split_num
+			)));
+		}
+		if record.final_split == Some(true) && !finals.insert(record.run_id) {
+			return Err(ZeroError::ImportError(format!(
+				"Line {line}: run {} has more than one final split",
+				record.run_id
+			)));
+		}
+		records.push(record);
+	}
+
+	records.sort_by_key(|r| (r.run_id, r.split_num));
+	Ok(records)
+}
+
+fn parse_required<T: std::str::FromStr>(value: Option<&str>, field: usize, line: usize) -> Result<T, ZeroError> {
+	match value {
+		Some(s) => parse_optional(Some(s), field, line).map(Option::unwrap),
+		None => Err(ZeroError::ImportError(format!(
+			"Line {line}: \"{}\" is missing",
+			COLUMNS[field]
+		))),
+	}
+}
+
+fn parse_optional<T: std::str::FromStr>(
+	value: Option<&str>,
+	field: usize,
+	line: usize,
+) -> Result<Option<T>, ZeroError> {
+	value
+		.map(|s| {
+			s.parse()
+				.map_err(|_| ZeroError::ImportError(format!("Line {line}: \"{s}\" is not a valid {}", COLUMNS[field])))
+		})
+		.transpose()
+}
+
+fn parse_bool(value: &str, field: usize, line: usize) -> Result<bool, ZeroError> {
+	match value.to_ascii_lowercase().as_str() {
+		"true" | "1" => Ok(true),
+		"false" | "0" => Ok(false),
+		_ => Err(ZeroError::ImportError(format!(
+			"Line {line}: \"{value}\" is not a valid {}",
+			COLUMNS[field]
+		))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn export_and_import_round_trip() -> Result<(), ZeroError> {
+		let db = Database::init()?;
+		db.import_run(vec![10, 20, 30, 40], &"default".to_string())?;
+		db.import_run(vec![15, 25], &"default".to_string())?;
+
+		let records = db.get_split_records("default")?;
+		let mut csv = Vec::new();
+		write_records(&records, &mut csv)?;
+
+		let headers = COLUMNS.map(str::to_owned);
+		let parsed = parse_records(&csv[..], &ColumnMapping::from_headers(&headers), Gamemode::GreenOrange)?;
+		assert_eq!(parsed, records);
+
+		assert_eq!(db.import_split_records("default", &parsed)?, 2);
+		assert_eq!(db.get_split_records("default")?.len(), 12);
+		Ok(())
+	}
+
+	#[test]
+	fn rejects_bad_rows() {
+		let mapping = ColumnMapping::from_headers(&["run".into(), "split_num".into(), "score".into()]);
+		assert!(parse_records(&b"run,split_num,score\n1,0,10\n"[..], &mapping, Gamemode::GreenOrange).is_err());
+
+		let mapping = ColumnMapping {
+			columns: [
+				Some(0),
+				None,
+				Some(1),
+				Some(2),
+				None,
+				None,
+				None,
+				None,
+				None,
+				None,
+				None,
+				None,
+			],
+		};
+		let bad_score = b"run,split_num,score\n1,0,1O0\n";
+		let out_of_range = b"run,split_num,score\n1,8,100\n";
+		let duplicate = b"run,split_num,score\n1,0,100\n1,0,200\n";
+		for input in [&bad_score[..], &out_of_range[..], &duplicate[..]] {
+			assert!(matches!(
+				parse_records(input, &mapping, Gamemode::GreenOrange),
+				Err(ZeroError::ImportError(_))
+			));
+		}
+
+		let mut mapping = mapping;
+		mapping.columns[FINAL] = Some(3);
+		let two_finals = b"run,split_num,score,final\n1,0,100,true\n1,1,200,true\n";
+		assert!(matches!(
+			parse_records(&two_finals[..], &mapping, Gamemode::GreenOrange),
+			Err(ZeroError::ImportError(_))
+		));
+		let one_final = b"run,split_num,score,final\n1,0,100,false\n1,1,200,true\n2,0,50,true\n";
+		assert_eq!(
+			parse_records(&one_final[..], &mapping, Gamemode::GreenOrange)
+				.unwrap()
+				.len(),
+			3
+		);
+	}
+}
@@ -0,0 +1,73 @@
+use std::{
+	sync::mpsc::{self, RecvTimeoutError},
+	thread,
+	time::Duration,
+};
+
+use log::warn;
+
+use crate::{
+	ZeroError, ZeroSplitter,
+	cli::flag,
+	config::{self, CONFIG},
+	ipc_thread, logger, open_database, system,
+};
+
+/// How long to wait for a frame from the game before checking on runs being saved
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Follow the game the way the window does, without the window: runs are split and saved the same way,
+/// and every split is printed to stdout. Runs until Ctrl+C or the console is closed, saving the run in progress first.
+pub fn run(args: &[String]) -> Result<(), ZeroError> {
+	config::load_config()?;
+	logger::set_level(CONFIG.get().unwrap().log_level);
+
+	let (tx, rx) = mpsc::channel();
+	thread::spawn(|| ipc_thread(tx));
+	let (db, categories) = open_database()?;
+	let mut splitter = ZeroSplitter::new(rx, db, categories);
+
+	if let Some(name) = flag(args, "--category")? {
+		let idx = splitter
+			.categories
+			.categories
+			.iter()
+			.position(|c| c.name == name)
+			.ok_or_else(|| ZeroError::ArgumentError(format!("No category named \"{name}\"")))?;
+		splitter.select_category(idx)?;
+	}
+	let events = splitter.subscribe();
+	if let Err(err) = system::catch_console_stop() {
+		warn!("Runs in progress won't be saved on Ctrl+C: {err}");
+	}
+	println!(
+		"Following ZeroRanger in {}, stop with Ctrl+C",
+		splitter.categories.current().name
+	);
+
+	while !system::stop_requested() {
+		match splitter.data_source.recv_timeout(POLL_INTERVAL) {
+			Ok(frame) => splitter.update_frame(frame),
+			Err(RecvTimeoutError::Timeout) => {}
+			Err(RecvTimeoutError::Disconnected) => return Err(ZeroError::GameListenerClosed),
+		}
+		splitter.poll_saves();
+		splitter.check_hook();
+		splitter.publish();
+
+		for event in events.try_iter() {
+			println!("{event}");
+		}
+		// Already logged by poll_saves
+		splitter.save_error = None;
+	}
+
+	println!("Stopping, saving the current run");
+	splitter.end_run();
+	splitter.finish_saves();
+	for event in events.try_iter() {
+		println!("{event}");
+	}
+	system::stopped();
+	Ok(())
+}
@@ -1,12 +1,23 @@
-use std::sync::Arc;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
 
-use log::error;
+use log::{error, info};
 use rusqlite::{
 	Connection, Result, ToSql, params,
 	types::{FromSql, ValueRef},
 };
 
-use crate::{Category, CategoryManager, Gamemode, Run};
+use crate::{
+	Category, CategoryMetadata, Gamemode, Run, VERSION, ZeroError,
+	archive::{ARCHIVE_VERSION, Archive, ArchivedCategory, ArchivedGoal, ArchivedRun, ArchivedSplit, RestoreSummary},
+	backup::BACKUP_DIR,
+	comparison::Goal,
+	integrity::{Issue, IssueKind, RepairSummary},
+	profile,
+};
 
 #[derive(Clone)]
 pub struct Database {
@@ -36,97 +47,92 @@ macro_rules! transaction {
 	}};
 }
 
-const CURRENT_SCHEMA_VERSION: i32 = 4;
+/// Schema migrations from `sql/migrations`, collected by the build script.
+/// Migration N (counting from 1) takes the schema from version N - 1 to N.
+const MIGRATIONS: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+pub const CURRENT_SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+/// Name of the database file in the profile's folder
+pub const DATABASE_FILE: &str = "sqlite.db3";
 
 impl Database {
-	pub fn init() -> Result<Self> {
-		let database = Database {
-			#[cfg(not(test))]
-			conn: Arc::new(Connection::open("./sqlite.db3")?),
-			#[cfg(test)]
-			conn: Arc::new(Connection::open_in_memory()?),
-		};
+	pub fn init() -> Result<Self, ZeroError> {
+		#[cfg(not(test))]
+		let conn = Connection::open(profile::path(DATABASE_FILE))?;
+		#[cfg(test)]
+		let conn = Connection::open_in_memory()?;
+
+		Self::open(conn)
+	}
+
+	/// Set up the tables on a new connection or bring an existing one up to the current schema
+	fn open(conn: Connection) -> Result<Self, ZeroError> {
+		let database = Database { conn: Arc::new(conn) };
 
 		// create tables if they don't exist
-		if !database.conn.table_exists(Some("main"), "categories")? {
+		let created = !database.conn.table_exists(Some("main"), "categories")?;
+		if created {
 			if let Err(err) = database.create_tables0() {
 				error!("Error creating tables: {}", err)
 			};
 			database.insert_new_category("default".to_owned(), Gamemode::GreenOrange)?;
 		}
 		// check version of schema (0 if they were just created)
-		let schema_version = database
-			.conn
-			.query_one("PRAGMA user_version", (), |row| row.get::<_, i32>(0))
-			.unwrap();
+		let schema_version = database.schema_version()?;
 
 		// migrate schema version if necessary
 		if schema_version < CURRENT_SCHEMA_VERSION {
-			database.migrate(schema_version)
+			// keep a copy of the old schema in case the migration goes wrong
+			if !created {
+				let path =
+					database.backup_into(&profile::path(BACKUP_DIR), &format!("pre-migration-v{schema_version}"))?;
+				info!("Backed up database to {} before migrating", path.display());
+			}
+			database.migrate(schema_version, CURRENT_SCHEMA_VERSION)?
 		} else if schema_version > CURRENT_SCHEMA_VERSION {
-			panic!(
-				"Schema version beyond program version!\n Schema version {schema_version}, program version {CURRENT_SCHEMA_VERSION}"
-			)
+			return Err(ZeroError::SchemaTooNew(schema_version));
 		}
 
 		Ok(database)
 	}
-	pub fn create_tables0(&self) -> Result<()> {
-		self.conn.execute("BEGIN TRANSACTION", ())?;
 
-		match (|| {
-			self.conn.pragma_update(Some("main"), "user_version", 0)?;
-			self.conn.execute(
-				"
-    CREATE TABLE IF NOT EXISTS categories (
-        id          INTEGER PRIMARY KEY,
-        name        TEXT UNIQUE NOT NULL,
-        mode        INTEGER NOT NULL
-    )
-    ",
-				(),
-			)?;
-
-			self.conn.execute(
-				"
-    CREATE TABLE IF NOT EXISTS runs (
-        id          INTEGER PRIMARY KEY,
-        category    INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE
-    ) 
-    ",
-				(),
-			)?;
-
-			self.conn.execute(
-				"
-    CREATE TABLE IF NOT EXISTS splits (
-        id          INTEGER PRIMARY KEY,
-        split_num   INTEGER NOT NULL,
-        score       INTEGER NOT NULL,
-        hits        INTEGER,
-        mult        INTEGER,
-        run_id      INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE
-    )
-    ",
-				(),
-			)?;
+	pub fn schema_version(&self) -> Result<i32> {
+		self.conn
+			.query_one("PRAGMA user_version", (), |row| row.get::<_, i32>(0))
+	}
 
-			Ok::<(), rusqlite::Error>(())
-		})() {
-			Ok(_) => {
-				self.conn.execute("COMMIT", ())?;
-				Ok(())
-			}
-			Err(err) => {
-				self.conn.execute("ROLLBACK", ())?;
-				Err(err)
-			}
+	/// Write a consistent copy of the database into `dir`, named after `prefix` and the current time.
+	/// A second backup in the same second gets a number after the time.
+	pub fn backup_into(&self, dir: &Path, prefix: &str) -> Result<PathBuf, ZeroError> {
+		fs::create_dir_all(dir)?;
+		let timestamp = self
+			.conn
+			.query_one("SELECT strftime('%Y-%m-%d_%H-%M-%S', 'now', 'localtime')", (), |row| {
+				row.get::<_, String>(0)
+			})?;
+		let mut path = dir.join(format!("{prefix}-{timestamp}.db3"));
+		let mut count = 1;
+		while path.exists() {
+			count += 1;
+			path = dir.join(format!("{prefix}-{timestamp}-{count}.db3"));
 		}
+		self.conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+		Ok(path)
+	}
+	/// Create the tables as they were in schema version 0. Migrations take them from there.
+	pub fn create_tables0(&self) -> Result<()> {
+		transaction!(self.conn, {
+			self.conn.pragma_update(Some("main"), "user_version", 0)?;
+			self.conn.execute_batch(include_str!("../sql/base_schema.sql"))?;
+		})
 	}
 
 	pub fn insert_new_category(&self, name: String, mode: Gamemode) -> Result<i64> {
-		self.conn
-			.execute("INSERT INTO categories VALUES(NULL, ?1, ?2)", params![name, mode])?;
+		self.conn.execute(
+			"INSERT INTO categories (name, mode) VALUES (?1, ?2)",
+			params![name, mode],
+		)?;
 
 		Ok(self.conn.last_insert_rowid())
 	}
@@ -142,24 +148,46 @@ impl Database {
 			params![new_name, category.id],
 		)
 	}
+	/// Every category, in the order they're listed in
 	pub fn get_categories(&self) -> Result<Vec<Category>> {
-		let mut statement = self.conn.prepare("SELECT name, mode, id FROM categories")?;
-		let rows = statement.query_map((), |row| {
-			Ok((
-				row.get::<_, String>(0)?,
-				row.get::<_, Gamemode>(1)?,
-				row.get::<_, i64>(2)?,
-			))
-		})?;
-		let categories = rows
-			.map(|r| r.unwrap())
-			.map(|(name, mode, id)| Category { id, mode, name })
-			.collect::<Vec<Category>>();
-		Ok(categories)
+		let mut statement = self.conn.prepare(
+			"SELECT name, mode, id, notes, game_version, ruleset, color, sort_order, archived FROM categories ORDER BY sort_order, id",
+		)?;
+		statement
+			.query_map((), |row| {
+				Ok(Category {
+					name: row.get(0)?,
+					mode: row.get(1)?,
+					id: row.get(2)?,
+					meta: CategoryMetadata {
+						notes: row.get(3)?,
+						game_version: row.get(4)?,
+						ruleset: row.get(5)?,
+						color: row.get(6)?,
+						sort_order: row.get(7)?,
+						archived: row.get(8)?,
+					},
+				})
+			})?
+			.collect()
+	}
+
+	pub fn update_category_metadata(&self, category_id: i64, meta: &CategoryMetadata) -> Result<usize> {
+		self.conn.execute(
+			"UPDATE categories SET notes = ?1, game_version = ?2, ruleset = ?3, color = ?4, sort_order = ?5, archived = ?6 WHERE id = ?7",
+			params![
+				meta.notes,
+				meta.game_version,
+				meta.ruleset,
+				meta.color,
+				meta.sort_order,
+				meta.archived,
+				category_id
+			],
+		)
 	}
 
-	pub fn insert_run(&self, category: &CategoryManager, run: &Run) -> Result<()> {
-		let category = category.current();
+	pub fn insert_run(&self, category: &Category, run: &Run) -> Result<()> {
 		self.conn.execute("BEGIN TRANSACTION", ())?;
 
 		match (|| {
@@ -176,8 +204,20 @@ impl Database {
 			for (num, &split) in run.splits().unwrap().iter().take_while(|&&s| s.score > 0).enumerate() {
 				let final_split = num == run.current_split().unwrap();
 				self.conn.execute(
-					"INSERT INTO splits (id, split_num, score, hits, mult, run_id, final, pattern_rank, dynamic_rank) VALUES(NULL, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-					params![num, split.score, 0, split.mult, run_id, final_split, split.pattern_rank, split.dynamic_rank],
+					"INSERT INTO splits (id, split_num, score, hits, mult, run_id, final, pattern_rank, dynamic_rank, peak_mult, mult_p2, peak_mult_p2) VALUES(NULL, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+					params![
+						num,
+						split.score,
+						0,
+						split.mult,
+						run_id,
+						final_split,
+						split.pattern_rank,
+						split.dynamic_rank,
+						split.peak_mult,
+						split.mult_p2,
+						split.peak_mult_p2
+					],
 				)?;
 			}
 
@@ -185,7 +225,7 @@ impl Database {
 		})() {
 			Ok(_) => {
 				self.conn.execute("COMMIT", ())?;
-				println!("Committing run with score {} to database", run.score().unwrap());
+				info!("Committing run with score {} to database", run.score().unwrap());
 				Ok(())
 			}
 			Err(err) => {
@@ -195,8 +235,8 @@ impl Database {
 		}
 	}
 
-	pub fn get_pb_run(&self, category: &CategoryManager) -> Result<(Vec<i32>, i32, Gamemode)> {
-		let category = category.current();
+	/// Splits, total score, mode and run id of the highest scoring run
+	pub fn get_pb_run(&self, category: &Category) -> Result<(Vec<i32>, i32, Gamemode, i64)> {
 		let mut statement = self.conn.prepare(include_str!("../sql/pb_splits.sql"))?;
 		let rows = statement.query_map(params![category.id], |row| {
 			Ok((
@@ -211,22 +251,22 @@ impl Database {
 		if splits.len() > 0 {
 			let scores: Vec<i32> = splits.iter().map(|s| s.0).collect();
 			let _hits: Vec<i32> = splits.iter().map(|s| s.1.unwrap_or(0)).collect();
-			let _run_id = splits[0].2;
+			let run_id = splits[0].2 as i64;
 			let mode = splits[0].3;
 
 			let total = scores.iter().sum();
 
-			Ok((scores, total, mode))
+			Ok((scores, total, mode, run_id))
 		} else {
 			Err(rusqlite::Error::QueryReturnedNoRows)
 		}
 	}
 
 	/// Get the highest core of each split for the category
-	pub fn get_gold_splits(&self, category: &CategoryManager) -> Result<Vec<i32>> {
+	pub fn get_gold_splits(&self, category: &Category) -> Result<Vec<i32>> {
 		let mut statement = self.conn.prepare(include_str!("../sql/best_splits.sql"))?;
 		statement
-			.query_map(params![category.current().id], |rows| rows.get(0))?
+			.query_map(params![category.id], |rows| rows.get(0))?
 			.collect::<Result<Vec<i32>>>()
 			.map(|v| {
 				if v.len() > 0 {
@@ -237,73 +277,600 @@ impl Database {
 			})?
 	}
 
-	fn migrate(&self, schema_version: i32) {
-		println!("Migrating database from {schema_version} to {CURRENT_SCHEMA_VERSION}");
+	/// Highest multiplier reached in each saved split of a run by split number, `None` for splits saved before
+	/// it was recorded
+	pub fn get_peak_mults(&self, run_id: i64) -> Result<Vec<(i32, Option<i64>)>> {
+		let mut statement = self
+			.conn
+			.prepare("SELECT split_num, peak_mult FROM splits WHERE run_id = ?1 ORDER BY split_num")?;
+		statement
+			.query_map(params![run_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+			.collect()
+	}
 
-		self.conn.execute("BEGIN TRANSACTION", ()).unwrap();
+	/// Number of runs saved for a category
+	pub fn count_runs(&self, category: &Category) -> Result<usize> {
+		self.conn.query_one(
+			"SELECT COUNT(*) FROM runs WHERE category = ?1",
+			params![category.id],
+			|row| row.get(0),
+		)
+	}
 
-		let mut current_schema = schema_version;
+	/// Save a goal for a category. Returns its id.
+	pub fn insert_goal(&self, category: &Category, name: &str, target: i32, splits: &[i32]) -> Result<i64> {
+		let mut goal_id = 0;
+		transaction!(self.conn, {
+			self.conn.execute(
+				"INSERT INTO goals (category, name, target) VALUES (?1, ?2, ?3)",
+				params![category.id, name, target],
+			)?;
+			goal_id = self.conn.last_insert_rowid();
+			for (num, score) in splits.iter().enumerate() {
+				self.conn.execute(
+					"INSERT INTO goal_splits (goal_id, split_num, score) VALUES (?1, ?2, ?3)",
+					params![goal_id, num, score],
+				)?;
+			}
+		})?;
+		Ok(goal_id)
+	}
 
-		match (|| {
-			while current_schema < CURRENT_SCHEMA_VERSION {
-				match current_schema {
-					0 => {
-						self.migrate0to1()?;
-						current_schema = 1
-					}
-					1 => {
-						self.migrate1to2()?;
-						current_schema = 2
-					}
-					2 => {
-						self.migrate2to3()?;
-						current_schema = 3
-					}
-					3 => {
-						self.migrate3to4()?;
-						current_schema = 4
-					}
-					_ => Err(rusqlite::Error::InvalidQuery)?,
-				};
+	pub fn get_goals(&self, category: &Category) -> Result<Vec<Goal>> {
+		let mut statement = self
+			.conn
+			.prepare("SELECT id, name, target FROM goals WHERE category = ?1 ORDER BY name")?;
+		let mut splits_stmt = self
+			.conn
+			.prepare("SELECT score FROM goal_splits WHERE goal_id = ?1 ORDER BY split_num")?;
+		statement
+			.query_map(params![category.id], |row| {
+				Ok(Goal {
+					id: row.get(0)?,
+					name: row.get(1)?,
+					target: row.get(2)?,
+					splits: Vec::new(),
+				})
+			})?
+			.map(|goal| {
+				let mut goal = goal?;
+				goal.splits = splits_stmt
+					.query_map(params![goal.id], |row| row.get(0))?
+					.collect::<Result<Vec<i32>>>()?;
+				Ok(goal)
+			})
+			.collect()
+	}
+
+	pub fn delete_goal(&self, goal_id: i64) -> Result<()> {
+		transaction!(self.conn, {
+			self.conn
+				.execute("DELETE FROM goal_splits WHERE goal_id = ?1", params![goal_id])?;
+			self.conn.execute("DELETE FROM goals WHERE id = ?1", params![goal_id])?;
+		})
+	}
+
+	/// How many runs reached, passed and ended on each split of a category.
+	/// Dates are local `YYYY-MM-DD` dates and both ends of the range are included. With a tag, only runs tagged with it count.
+	/// Imported runs don't record where they ended, so they aren't counted.
+	pub fn get_pass_rates(
+		&self,
+		category: &Category,
+		from: Option<&str>,
+		to: Option<&str>,
+		tag: Option<&str>,
+	) -> Result<Vec<PassRate>> {
+		let mut statement = self.conn.prepare(include_str!("../sql/pass_rate.sql"))?;
+		statement
+			.query_map(params![category.id, from, to, tag], |row| {
+				Ok(PassRate {
+					split_num: row.get(0)?,
+					reached: row.get(1)?,
+					passed: row.get(2)?,
+					ended: row.get(3)?,
+				})
+			})?
+			.collect()
+	}
+
+	/// Every run of a category with its tags and split notes, newest first.
+	/// With a tag, only runs tagged with it.
+	pub fn get_run_history(&self, category: &Category, tag: Option<&str>) -> Result<Vec<HistoryRun>> {
+		let mut runs_stmt = self.conn.prepare(
+			"SELECT id, datetime, imported FROM runs WHERE category = ?1 \
+			AND (?2 IS NULL OR id IN (SELECT run_id FROM run_tags WHERE tag = ?2)) ORDER BY id DESC",
+		)?;
+		let mut splits_stmt = self.conn.prepare(
+			"SELECT splits.id, split_num, score, final, coalesce(note, '') FROM splits \
+			LEFT JOIN split_notes ON split_notes.split_id = splits.id WHERE run_id = ?1 ORDER BY split_num",
+		)?;
+		runs_stmt
+			.query_map(params![category.id, tag], |row| {
+				Ok(HistoryRun {
+					id: row.get(0)?,
+					datetime: row.get(1)?,
+					imported: row.get(2)?,
+					tags: Vec::new(),
+					splits: Vec::new(),
+				})
+			})?
+			.map(|run| {
+				let mut run = run?;
+				run.tags = self.get_run_tags(run.id)?;
+				run.splits = splits_stmt
+					.query_map(params![run.id], |row| {
+						Ok(HistorySplit {
+							id: row.get(0)?,
+							split_num: row.get(1)?,
+							score: row.get(2)?,
+							final_split: row.get(3)?,
+							note: row.get(4)?,
+						})
+					})?
+					.collect::<Result<Vec<_>>>()?;
+				Ok(run)
+			})
+			.collect()
+	}
+
+	pub fn get_run_tags(&self, run_id: i64) -> Result<Vec<String>> {
+		let mut statement = self
+			.conn
+			.prepare("SELECT tag FROM run_tags WHERE run_id = ?1 ORDER BY tag")?;
+		statement.query_map(params![run_id], |row| row.get(0))?.collect()
+	}
+
+	/// Every tag used on a run of the category, sorted
+	pub fn get_category_tags(&self, category: &Category) -> Result<Vec<String>> {
+		let mut statement = self.conn.prepare(
+			"SELECT DISTINCT tag FROM run_tags INNER JOIN runs ON runs.id = run_tags.run_id \
+			WHERE runs.category = ?1 ORDER BY tag",
+		)?;
+		statement.query_map(params![category.id], |row| row.get(0))?.collect()
+	}
+
+	/// Replace the tags of a run
+	pub fn set_run_tags(&self, run_id: i64, tags: &[String]) -> Result<()> {
+		transaction!(self.conn, {
+			self.conn
+				.execute("DELETE FROM run_tags WHERE run_id = ?1", params![run_id])?;
+			self.insert_run_tags(run_id, tags)?;
+		})
+	}
+
+	/// Add tags to a run without starting a transaction
+	fn insert_run_tags(&self, run_id: i64, tags: &[String]) -> Result<()> {
+		for tag in tags {
+			self.conn.execute(
+				"INSERT OR IGNORE INTO run_tags (run_id, tag) VALUES (?1, ?2)",
+				params![run_id, tag],
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Set the note on a split. An empty note removes it.
+	pub fn set_split_note(&self, split_id: i64, note: &str) -> Result<()> {
+		if note.is_empty() {
+			self.conn
+				.execute("DELETE FROM split_notes WHERE split_id = ?1", params![split_id])?;
+		} else {
+			self.conn.execute(
+				"INSERT INTO split_notes (split_id, note) VALUES (?1, ?2) \
+				ON CONFLICT(split_id) DO UPDATE SET note = excluded.note",
+				params![split_id, note],
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Look for rows that break the assumptions the rest of the program makes about runs and splits.
+	/// Nothing is changed.
+	pub fn check_integrity(&self) -> Result<Vec<Issue>> {
+		let mut issues = Vec::new();
+		let mut add = |kind, runs: Vec<i64>, rows: usize| {
+			if rows > 0 {
+				issues.push(Issue { kind, runs, rows });
 			}
-			Ok::<(), rusqlite::Error>(())
-		})() {
-			Ok(_) => {
-				self.conn.execute("COMMIT", ()).unwrap();
-				println!("Migration successful")
+		};
+
+		let run_ids = |sql: &str| -> Result<Vec<i64>> {
+			let mut statement = self.conn.prepare(sql)?;
+			statement.query_map((), |row| row.get(0))?.collect()
+		};
+		let count = |sql: &str| -> Result<usize> { self.conn.query_one(sql, (), |row| row.get(0)) };
+
+		let orphan_runs = run_ids("SELECT id FROM runs WHERE category NOT IN (SELECT id FROM categories) ORDER BY id")?;
+		let rows = orphan_runs.len();
+		add(IssueKind::OrphanRuns, orphan_runs, rows);
+
+		let orphan_splits =
+			run_ids("SELECT DISTINCT run_id FROM splits WHERE run_id NOT IN (SELECT id FROM runs) ORDER BY run_id")?;
+		let rows = count("SELECT count(*) FROM splits WHERE run_id NOT IN (SELECT id FROM runs)")?;
+		add(IssueKind::OrphanSplits, orphan_splits, rows);
+
+		let empty_runs = run_ids("SELECT id FROM runs WHERE id NOT IN (SELECT run_id FROM splits) ORDER BY id")?;
+		let rows = empty_runs.len();
+		add(IssueKind::EmptyRuns, empty_runs, rows);
+
+		let duplicates = run_ids(
+			"SELECT run_id FROM splits GROUP BY run_id HAVING count(*) > count(DISTINCT split_num) ORDER BY run_id",
+		)?;
+		let rows = count(
+			"SELECT count(*) FROM splits WHERE id NOT IN (SELECT min(id) FROM splits GROUP BY run_id, split_num)",
+		)?;
+		add(IssueKind::DuplicateSplits, duplicates, rows);
+
+		let finals = run_ids(
+			"SELECT run_id FROM splits WHERE final = true GROUP BY run_id HAVING count(DISTINCT split_num) > 1 ORDER BY run_id",
+		)?;
+		let rows = count(
+			"SELECT count(*) FROM splits WHERE final = true AND split_num < \
+			(SELECT max(split_num) FROM splits AS last WHERE last.run_id = splits.run_id AND last.final = true)",
+		)?;
+		add(IssueKind::MultipleFinals, finals, rows);
+
+		// Split counts and numbers only make sense against the mode of the run's category
+		let mut statement = self.conn.prepare(
+			"SELECT runs.id, categories.mode, count(*), count(DISTINCT split_num), min(split_num), max(split_num) \
+			FROM runs INNER JOIN categories ON categories.id = runs.category \
+			INNER JOIN splits ON splits.run_id = runs.id GROUP BY runs.id ORDER BY runs.id",
+		)?;
+		let (mut too_many, mut mismatched) = (Vec::new(), Vec::new());
+		for row in statement.query_map((), |row| {
+			Ok((
+				row.get::<_, i64>(0)?,
+				row.get::<_, Gamemode>(1)?,
+				row.get::<_, usize>(2)?,
+				row.get::<_, usize>(3)?,
+				row.get::<_, i64>(4)?,
+				row.get::<_, i64>(5)?,
+			))
+		})? {
+			let (run_id, mode, splits, distinct, min, max) = row?;
+			let expected = match mode {
+				// No split layout to check against yet
+				Gamemode::BlackOnion => continue,
+				mode => mode.splits(),
+			};
+			// Duplicates are reported on their own, only count them here if they don't explain it
+			if splits > expected && distinct > expected {
+				too_many.push(run_id);
 			}
-			Err(err) => {
-				self.conn.execute("ROLLBACK", ()).unwrap();
-				panic!("Migration failed! {err}")
+			if min < 0 || max >= expected as i64 {
+				mismatched.push(run_id);
 			}
 		}
+		let rows = too_many.len();
+		add(IssueKind::TooManySplits, too_many, rows);
+		let rows = mismatched.len();
+		add(IssueKind::ModeMismatch, mismatched, rows);
+
+		let rows = count(
+			"SELECT (SELECT count(*) FROM run_tags WHERE run_id NOT IN (SELECT id FROM runs)) \
+			+ (SELECT count(*) FROM split_notes WHERE split_id NOT IN (SELECT id FROM splits)) \
+			+ (SELECT count(*) FROM goals WHERE category NOT IN (SELECT id FROM categories)) \
+			+ (SELECT count(*) FROM goal_splits WHERE goal_id NOT IN (SELECT id FROM goals))",
+		)?;
+		add(IssueKind::OrphanRows, Vec::new(), rows);
+
+		Ok(issues)
 	}
 
-	fn migrate0to1(&self) -> Result<usize> {
-		println!("Migrating schema 0 to 1...");
-		self.conn.pragma_update(Some("main"), "user_version", 1)?;
-		self.conn.execute("ALTER TABLE splits ADD COLUMN final BOOLEAN", ())
+	/// Fix every issue from [`Database::check_integrity`] that can be fixed without guessing,
+	/// in one transaction. Runs in the wrong mode or with too many splits are left alone.
+	pub fn repair_integrity(&self) -> Result<RepairSummary> {
+		let mut summary = RepairSummary::default();
+		transaction!(self.conn, {
+			let conn = &self.conn;
+			summary.runs_removed +=
+				conn.execute("DELETE FROM runs WHERE category NOT IN (SELECT id FROM categories)", ())?;
+			summary.splits_removed +=
+				conn.execute("DELETE FROM splits WHERE run_id NOT IN (SELECT id FROM runs)", ())?;
+			// Keep the first copy of a split that was saved twice
+			summary.splits_removed += conn.execute(
+				"DELETE FROM splits WHERE id NOT IN (SELECT min(id) FROM splits GROUP BY run_id, split_num)",
+				(),
+			)?;
+			// A run ends once, on the last split it reached
+			summary.finals_cleared += conn.execute(
+				"UPDATE splits SET final = false WHERE final = true AND split_num < \
+				(SELECT max(split_num) FROM splits AS last WHERE last.run_id = splits.run_id AND last.final = true)",
+				(),
+			)?;
+			summary.runs_removed += conn.execute("DELETE FROM runs WHERE id NOT IN (SELECT run_id FROM splits)", ())?;
+
+			summary.other_rows_removed +=
+				conn.execute("DELETE FROM run_tags WHERE run_id NOT IN (SELECT id FROM runs)", ())?;
+			summary.other_rows_removed += conn.execute(
+				"DELETE FROM split_notes WHERE split_id NOT IN (SELECT id FROM splits)",
+				(),
+			)?;
+			summary.other_rows_removed += conn.execute(
+				"DELETE FROM goals WHERE category NOT IN (SELECT id FROM categories)",
+				(),
+			)?;
+			summary.other_rows_removed += conn.execute(
+				"DELETE FROM goal_splits WHERE goal_id NOT IN (SELECT id FROM goals)",
+				(),
+			)?;
+		})?;
+		Ok(summary)
 	}
 
-	fn migrate1to2(&self) -> Result<usize> {
-		println!("Migrating schema 1 to 2...");
-		self.conn.pragma_update(Some("main"), "user_version", 2)?;
-		self.conn.execute("ALTER TABLE runs ADD COLUMN datetime INTEGER", ())
+	/// Apply the migrations taking the schema from version `from` to version `to` in one transaction
+	fn migrate(&self, from: i32, to: i32) -> Result<()> {
+		info!("Migrating database from {from} to {to}");
+
+		let result = transaction!(self.conn, {
+			for (version, (name, sql)) in MIGRATIONS.iter().enumerate().take(to as usize).skip(from as usize) {
+				info!("Migrating schema {version} to {} ({name})...", version + 1);
+				self.conn.execute_batch(sql)?;
+				self.conn.pragma_update(Some("main"), "user_version", version + 1)?;
+			}
+		});
+
+		match &result {
+			Ok(_) => info!("Migration successful"),
+			Err(err) => error!("Migration failed! {err}"),
+		}
+		result
 	}
 
-	fn migrate2to3(&self) -> Result<()> {
-		println!("Migrating schema 2 to 3...");
-		self.conn.pragma_update(Some("main"), "user_version", 3)?;
-		self.conn.execute_batch(
-			"ALTER TABLE splits ADD COLUMN pattern_rank REAL;
-			ALTER TABLE splits ADD COLUMN dynamic_rank REAL;",
-		)
+	/// Get every split of every run in the category, ordered by run then split
+	pub fn get_split_records(&self, category_name: &str) -> Result<Vec<SplitRecord>> {
+		let mut statement = self.conn.prepare(include_str!("../sql/category_splits.sql"))?;
+		statement
+			.query_map(params![category_name], |row| {
+				Ok(SplitRecord {
+					run_id: row.get(0)?,
+					datetime: row.get(1)?,
+					split_num: row.get(2)?,
+					score: row.get(3)?,
+					mult: row.get(4)?,
+					pattern_rank: row.get(5)?,
+					dynamic_rank: row.get(6)?,
+					final_split: row.get(7)?,
+					imported: row.get(8)?,
+					peak_mult: row.get(9)?,
+					mult_p2: row.get(10)?,
+					peak_mult_p2: row.get(11)?,
+				})
+			})?
+			.collect()
+	}
+
+	/// Insert split records as new runs in the category, grouped by their `run_id`.
+	/// The original run ids are not kept. Returns the number of runs inserted.
+	pub fn import_split_records(&self, category_name: &str, records: &[SplitRecord]) -> Result<usize> {
+		let mut run_count = 0;
+		transaction!(self.conn, {
+			let category_id =
+				self.conn
+					.query_one("SELECT id FROM categories WHERE name=?1", params![category_name], |r| {
+						r.get::<_, i64>(0)
+					})?;
+
+			let mut last_run = None;
+			let mut new_run_id = 0;
+			for record in records {
+				if last_run != Some(record.run_id) {
+					self.conn.execute(
+						"INSERT INTO runs (category, datetime, imported) VALUES (?1, ?2, ?3)",
+						params![category_id, record.datetime, record.imported.unwrap_or(true)],
+					)?;
+					new_run_id = self.conn.last_insert_rowid();
+					last_run = Some(record.run_id);
+					run_count += 1;
+				}
+				self.conn.execute(
+					"INSERT INTO splits (split_num, score, hits, mult, run_id, final, pattern_rank, dynamic_rank, peak_mult, mult_p2, peak_mult_p2) VALUES (?1, ?2, 0, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+					params![
+						record.split_num,
+						record.score,
+						record.mult,
+						new_run_id,
+						record.final_split,
+						record.pattern_rank,
+						record.dynamic_rank,
+						record.peak_mult,
+						record.mult_p2,
+						record.peak_mult_p2
+					],
+				)?;
+			}
+		})?;
+		Ok(run_count)
 	}
 
-	fn migrate3to4(&self) -> Result<usize> {
-		println!("Migrating schema 3 to 4...");
-		self.conn.pragma_update(Some("main"), "user_version", 4)?;
-		self.conn.execute("ALTER TABLE runs ADD COLUMN imported BOOLEAN", ())
+	/// Copy every category, run and split into an archive
+	pub fn dump_archive(&self) -> Result<Archive> {
+		let mut categories = Vec::new();
+		for category in self.get_categories()? {
+			let mut runs_stmt = self
+				.conn
+				.prepare("SELECT id, datetime, imported FROM runs WHERE category = ?1 ORDER BY id")?;
+			let runs = runs_stmt
+				.query_map(params![category.id], |row| {
+					Ok(ArchivedRun {
+						id: row.get(0)?,
+						datetime: row.get(1)?,
+						imported: row.get(2)?,
+						splits: Vec::new(),
+						tags: Vec::new(),
+					})
+				})?
+				.map(|run| {
+					let mut run = run?;
+					run.splits = self.get_archived_splits(run.id)?;
+					run.tags = self.get_run_tags(run.id)?;
+					Ok(run)
+				})
+				.collect::<Result<Vec<ArchivedRun>>>()?;
+
+			let goals = self
+				.get_goals(&category)?
+				.into_iter()
+				.map(|goal| ArchivedGoal {
+					name: goal.name,
+					target: goal.target,
+					splits: goal.splits,
+				})
+				.collect();
+
+			categories.push(ArchivedCategory {
+				id: category.id,
+				name: category.name,
+				mode: category.mode,
+				meta: category.meta,
+				runs,
+				goals,
+			});
+		}
+
+		Ok(Archive {
+			archive_version: ARCHIVE_VERSION,
+			schema_version: CURRENT_SCHEMA_VERSION,
+			program_version: VERSION.to_owned(),
+			categories,
+		})
+	}
+
+	fn get_archived_splits(&self, run_id: i64) -> Result<Vec<ArchivedSplit>> {
+		let mut statement = self.conn.prepare(
+			"SELECT splits.id, split_num, score, hits, mult, final, pattern_rank, dynamic_rank, peak_mult, mult_p2, peak_mult_p2, coalesce(note, '') FROM splits LEFT JOIN split_notes ON split_notes.split_id = splits.id WHERE run_id = ?1 ORDER BY split_num",
+		)?;
+		statement
+			.query_map(params![run_id], |row| {
+				Ok(ArchivedSplit {
+					id: row.get(0)?,
+					split_num: row.get(1)?,
+					score: row.get(2)?,
+					hits: row.get(3)?,
+					mult: row.get(4)?,
+					final_split: row.get(5)?,
+					pattern_rank: row.get(6)?,
+					dynamic_rank: row.get(7)?,
+					peak_mult: row.get(8)?,
+					mult_p2: row.get(9)?,
+					peak_mult_p2: row.get(10)?,
+					note: row.get(11)?,
+				})
+			})?
+			.collect()
+	}
+
+	/// Merge an archive into the database. Categories are matched by name, and runs that
+	/// already exist (same datetime and same split scores) or goals with a name that's
+	/// already taken are skipped. The caller is
+	/// responsible for checking the archive is compatible.
+	pub fn restore_archive(&self, archive: &Archive) -> Result<RestoreSummary> {
+		let mut summary = RestoreSummary::default();
+		transaction!(self.conn, {
+			for category in &archive.categories {
+				// A category with the same name but another mode gets restored next to it under a new name
+				let mut name = category.name.clone();
+				let mut attempt = 1;
+				let category_id = loop {
+					let existing =
+						self.conn
+							.query_one("SELECT id, mode FROM categories WHERE name = ?1", params![name], |r| {
+								Ok((r.get::<_, i64>(0)?, r.get::<_, Gamemode>(1)?))
+							});
+					match existing {
+						Ok((id, mode)) if mode == category.mode => break id,
+						Ok(_) => {
+							name = match attempt {
+								1 => format!("{} ({:?})", category.name, category.mode),
+								n => format!("{} ({:?} {n})", category.name, category.mode),
+							};
+							attempt += 1;
+						}
+						Err(rusqlite::Error::QueryReturnedNoRows) => {
+							summary.categories_added += 1;
+							if name != category.name {
+								summary.categories_renamed += 1;
+							}
+							let id = self.insert_new_category(name, category.mode)?;
+							self.update_category_metadata(id, &category.meta)?;
+							break id;
+						}
+						Err(err) => return Err(err),
+					}
+				};
+
+				// Fingerprints of runs already in this category
+				let mut known = Vec::new();
+				let mut runs_stmt = self.conn.prepare("SELECT id, datetime FROM runs WHERE category = ?1")?;
+				for run in runs_stmt.query_map(params![category_id], |row| {
+					Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+				})? {
+					let (id, datetime) = run?;
+					known.push(run_fingerprint(&datetime, &self.get_archived_splits(id)?));
+				}
+
+				for run in &category.runs {
+					let fingerprint = run_fingerprint(&run.datetime, &run.splits);
+					if known.contains(&fingerprint) {
+						summary.runs_skipped += 1;
+						continue;
+					}
+
+					self.conn.execute(
+						"INSERT INTO runs (category, datetime, imported) VALUES (?1, ?2, ?3)",
+						params![category_id, run.datetime, run.imported],
+					)?;
+					let run_id = self.conn.last_insert_rowid();
+					for split in &run.splits {
+						self.conn.execute(
+							"INSERT INTO splits (split_num, score, hits, mult, run_id, final, pattern_rank, dynamic_rank, peak_mult, mult_p2, peak_mult_p2) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+							params![
+								split.split_num,
+								split.score,
+								split.hits,
+								split.mult,
+								run_id,
+								split.final_split,
+								split.pattern_rank,
+								split.dynamic_rank,
+								split.peak_mult,
+								split.mult_p2,
+								split.peak_mult_p2
+							],
+						)?;
+						if !split.note.is_empty() {
+							self.set_split_note(self.conn.last_insert_rowid(), &split.note)?;
+						}
+					}
+					self.insert_run_tags(run_id, &run.tags)?;
+					known.push(fingerprint);
+					summary.runs_added += 1;
+				}
+
+				for goal in &category.goals {
+					let exists = self.conn.query_one(
+						"SELECT count(*) FROM goals WHERE category = ?1 AND name = ?2",
+						params![category_id, goal.name],
+						|row| row.get::<_, i64>(0),
+					)? > 0;
+					if !exists {
+						self.conn.execute(
+							"INSERT INTO goals (category, name, target) VALUES (?1, ?2, ?3)",
+							params![category_id, goal.name, goal.target],
+						)?;
+						let goal_id = self.conn.last_insert_rowid();
+						for (num, score) in goal.splits.iter().enumerate() {
+							self.conn.execute(
+								"INSERT INTO goal_splits (goal_id, split_num, score) VALUES (?1, ?2, ?3)",
+								params![goal_id, num, score],
+							)?;
+						}
+						summary.goals_added += 1;
+					}
+				}
+			}
+		})?;
+		Ok(summary)
 	}
 
 	pub fn import_run(&self, splits: Vec<i32>, category_name: &String) -> Result<()> {
@@ -332,6 +899,98 @@ impl Database {
 	}
 }
 
+/// Identifies a run by when it happened and what it scored, ignoring database ids
+fn run_fingerprint(datetime: &Option<String>, splits: &[ArchivedSplit]) -> (Option<String>, Vec<(i32, i32)>) {
+	(
+		datetime.clone(),
+		splits.iter().map(|s| (s.split_num, s.score)).collect(),
+	)
+}
+
+/// Attempt counts for one split of a category
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassRate {
+	pub split_num: usize,
+	/// Runs that got to this split
+	pub reached: u32,
+	/// Runs that got past this split
+	pub passed: u32,
+	/// Runs that were reset or died on this split
+	pub ended: u32,
+}
+
+impl PassRate {
+	pub fn percentage(&self) -> f32 {
+		if self.reached == 0 {
+			0.0
+		} else {
+			self.passed as f32 * 100.0 / self.reached as f32
+		}
+	}
+}
+
+/// A saved run as listed in the history
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryRun {
+	pub id: i64,
+	pub datetime: Option<String>,
+	pub imported: Option<bool>,
+	pub tags: Vec<String>,
+	pub splits: Vec<HistorySplit>,
+}
+
+impl HistoryRun {
+	pub fn total(&self) -> i32 {
+		self.splits.iter().map(|s| s.score).sum()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySplit {
+	pub id: i64,
+	pub split_num: i32,
+	pub score: i32,
+	pub final_split: Option<bool>,
+	/// Empty when the split has no note
+	pub note: String,
+}
+
+/// One row of the `splits` table joined with its run, as exported to and imported from CSV
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SplitRecord {
+	pub run_id: i64,
+	pub datetime: Option<String>,
+	pub split_num: i32,
+	pub score: i32,
+	pub mult: Option<i64>,
+	pub pattern_rank: Option<f64>,
+	pub dynamic_rank: Option<f64>,
+	pub final_split: Option<bool>,
+	pub imported: Option<bool>,
+	pub peak_mult: Option<i64>,
+	pub mult_p2: Option<i64>,
+	pub peak_mult_p2: Option<i64>,
+}
+
+impl SplitRecord {
+	/// A split with only a score, for tests to fill in the rest
+	#[cfg(test)]
+	pub fn new(run_id: i64, split_num: i32, score: i32) -> Self {
+		SplitRecord {
+			run_id,
+			split_num,
+			score,
+			..Default::default()
+		}
+	}
+
+	/// Whether the split was played to the end, rather than being where the run was reset or died.
+	/// The last split of a mode always counts as played.
+	pub fn is_whole(&self, mode: Gamemode) -> bool {
+		self.final_split != Some(true) || self.split_num as usize + 1 == mode.splits()
+	}
+}
+
 impl ToSql for Gamemode {
 	fn to_sql(&self) -> Result<rusqlite::types::ToSqlOutput<'_>> {
 		match self {
@@ -367,26 +1026,143 @@ mod tests {
 	use crate::{
 		Category,
 		database::{self, Database},
+		worker::DbWorker,
 	};
 
 	#[test]
-	fn import_and_get_pb() -> Result<()> {
-		let db = Database::init()?;
+	fn import_and_get_pb() -> Result<(), crate::ZeroError> {
+		let db = DbWorker::spawn()?;
 
 		println!("Importing run...");
-		db.import_run(vec![10, 20, 30, 40], &"default".to_string())?;
+		db.call(|db| Ok(db.import_run(vec![10, 20, 30, 40], &"default".to_string())?))?;
 
-		let mut categories = crate::CategoryManager {
-			categories: vec![],
-			current: 0,
-			comparison_cache: vec![],
-		};
-		categories.load(&db);
+		let mut categories = crate::CategoryManager::open(&db)?;
+		categories.wait(&db)?;
 
 		println!("Getting PB...");
-		let pb = db.get_pb_run(&categories)?;
+		let category = categories.current().clone();
+		let pb = db.call(move |db| Ok(db.get_pb_run(&category)?))?;
 
 		assert!(pb.0 == vec![10, 20, 30, 40]);
+		assert_eq!(categories.stats().pb, 100);
+		assert_eq!(categories.stats().attempts, 1);
+
+		// the stats only change once they're refreshed
+		db.call(|db| Ok(db.import_run(vec![5, 50], &"default".to_string())?))?;
+		assert_eq!(categories.stats().sum_of_best, 100);
+		categories.refresh_stats(&db);
+		categories.wait(&db)?;
+		assert_eq!(categories.stats().gold_splits, vec![10, 50, 30, 40]);
+		assert_eq!(categories.stats().sum_of_best, 130);
+		assert_eq!(categories.stats().attempts, 2);
+
+		// the PB comparison is calculated after whatever was sent to the database before it
+		let _ = db.request(|db| Ok(db.import_run(vec![100, 100], &"default".to_string())?));
+		categories.refresh_comparison(&db);
+		categories.wait(&db)?;
+		assert_eq!(categories.get_comparison(), [100, 100]);
+		Ok(())
+	}
+
+	#[test]
+	fn backups_in_the_same_second() -> Result<(), crate::ZeroError> {
+		let db = Database::init()?;
+		let dir = std::env::temp_dir().join(format!("zerosplitter-backups-{}", std::process::id()));
+		let first = db.backup_into(&dir, "test")?;
+		let second = db.backup_into(&dir, "test")?;
+		assert_ne!(first, second);
+		assert!(first.exists() && second.exists());
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn tags_and_notes() -> Result<(), crate::ZeroError> {
+		let db = Database::init()?;
+		db.import_run(vec![10, 20], &"default".to_string())?;
+		db.import_run(vec![30], &"default".to_string())?;
+		let category = db.get_categories()?.remove(0);
+		// the run scoring 30 is the newest
+		let runs = db.get_run_history(&category, None)?;
+		assert_eq!(runs.iter().map(|r| r.total()).collect::<Vec<_>>(), vec![30, 30]);
+
+		let first = runs[1].id;
+		db.set_run_tags(first, &["practice".to_owned(), "new route".to_owned()])?;
+		db.set_split_note(runs[1].splits[1].id, "dropped a bomb")?;
+		assert_eq!(db.get_category_tags(&category)?, vec!["new route", "practice"]);
+
+		let tagged = db.get_run_history(&category, Some("practice"))?;
+		assert_eq!(tagged.len(), 1);
+		assert_eq!(tagged[0].id, first);
+		assert_eq!(tagged[0].splits[1].note, "dropped a bomb");
+
+		// clearing a note or tags removes them
+		db.set_split_note(runs[1].splits[1].id, "")?;
+		db.set_run_tags(first, &[])?;
+		assert!(db.get_run_history(&category, None)?[1].splits[1].note.is_empty());
+		assert!(db.get_category_tags(&category)?.is_empty());
+		Ok(())
+	}
+
+	/// Build a database at an old schema version, the way that version of the program left it
+	fn database_at(version: i32) -> Result<Database> {
+		let db = Database {
+			conn: Arc::new(Connection::open_in_memory()?),
+		};
+		db.create_tables0()?;
+		db.migrate(0, version)?;
+		Ok(db)
+	}
+
+	fn schema_sql(db: &Database) -> Result<Vec<String>> {
+		let mut statement = db
+			.conn
+			.prepare("SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY name")?;
+		statement.query_map((), |row| row.get(0))?.collect()
+	}
+
+	#[test]
+	fn each_migration_step() -> Result<()> {
+		for version in 1..=database::CURRENT_SCHEMA_VERSION {
+			let db = database_at(version - 1)?;
+			let before = schema_sql(&db)?;
+
+			db.migrate(version - 1, version)?;
+			assert_eq!(db.schema_version()?, version);
+			assert_ne!(schema_sql(&db)?, before, "migration to {version} changed nothing");
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn upgrade_from_every_version() -> Result<(), crate::ZeroError> {
+		for version in 0..=database::CURRENT_SCHEMA_VERSION {
+			let db = database_at(version)?;
+			// only use columns that have existed since version 0
+			db.conn
+				.execute("INSERT INTO categories (name, mode) VALUES ('old', 1)", ())?;
+			db.conn.execute("INSERT INTO runs (category) VALUES (1)", ())?;
+			for (num, score) in [100, 200, 300].iter().enumerate() {
+				db.conn.execute(
+					"INSERT INTO splits (split_num, score, hits, mult, run_id) VALUES (?1, ?2, 0, 5, 1)",
+					rusqlite::params![num, score],
+				)?;
+			}
+
+			db.migrate(version, database::CURRENT_SCHEMA_VERSION)?;
+			assert_eq!(db.schema_version()?, database::CURRENT_SCHEMA_VERSION);
+
+			let categories = db.get_categories()?;
+			assert_eq!(categories.len(), 1);
+			assert_eq!(categories[0].mode, Gamemode::WhiteVanilla);
+
+			let records = db.get_split_records("old")?;
+			assert_eq!(records.iter().map(|r| r.score).collect::<Vec<_>>(), vec![100, 200, 300]);
+			assert!(records.iter().all(|r| r.mult == Some(5) && r.final_split.is_none()));
+
+			// the upgraded database takes runs with every current column
+			assert_eq!(db.import_split_records("old", &records)?, 1);
+		}
 		Ok(())
 	}
 }
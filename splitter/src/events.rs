@@ -0,0 +1,112 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::Gamemode;
+
+/// Something that happened during a run, for anything following along outside the window.
+/// Subscribe with `ZeroSplitter::subscribe`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+	/// A run started. White Vanilla runs can start on a later stage, so it isn't always the first split.
+	Started {
+		category: String,
+		split: usize,
+		name: String,
+		/// Name of the comparison the run is compared against
+		comparison: String,
+	},
+	/// A split was finished
+	Split {
+		split: usize,
+		name: String,
+		score: i32,
+		total: i32,
+		/// Difference to the comparison's total at the end of the split, `None` without a comparison
+		delta: Option<i32>,
+	},
+	/// A split was finished with more score than any earlier run got on it. Follows its `Split`.
+	Gold {
+		split: usize,
+		name: String,
+		score: i32,
+		previous: i32,
+	},
+	/// The run that just ended beat the PB. Comes right before its `Ended`.
+	PersonalBest { total: i32, previous: i32 },
+	/// The run was reset, ended on a death or went back to the menu, and is being saved
+	Ended {
+		total: i32,
+		split: usize,
+		pb: bool,
+		/// The game was played to the end: TLB or the credits in Green Orange, the end of Stage EX in White Vanilla
+		cleared: bool,
+	},
+	/// Another category was picked
+	CategoryChanged { category: String, mode: Gamemode },
+	/// The game started or stopped sending frames
+	HookStatus { connected: bool },
+}
+
+impl fmt::Display for RunEvent {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RunEvent::Started { category, name, .. } => write!(f, "Run started in {category} at {name}"),
+			RunEvent::Split {
+				name,
+				score,
+				total,
+				delta,
+				..
+			} => {
+				write!(f, "Split {name}: {score} (total {total}")?;
+				if let Some(delta) = delta {
+					write!(f, ", {delta:+}")?;
+				}
+				write!(f, ")")
+			}
+			RunEvent::Gold {
+				name, score, previous, ..
+			} => write!(f, "Gold on {name}: {score}, was {previous}"),
+			RunEvent::PersonalBest { total, previous } => write!(f, "New PB: {total}, was {previous}"),
+			RunEvent::Ended { total, pb, .. } => {
+				write!(f, "Run ended with {total}")?;
+				if *pb {
+					write!(f, ", new PB!")?;
+				}
+				Ok(())
+			}
+			RunEvent::CategoryChanged { category, mode } => write!(f, "Switched to {category} ({mode:?})"),
+			RunEvent::HookStatus { connected: true } => write!(f, "Connected to ZeroRanger"),
+			RunEvent::HookStatus { connected: false } => write!(f, "Lost ZeroRanger"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn serialises_with_tag() {
+		let event = RunEvent::Split {
+			split: 2,
+			name: "1-3".to_owned(),
+			score: 1500,
+			total: 4000,
+			delta: Some(-250),
+		};
+		assert_eq!(
+			serde_json::to_value(&event).unwrap(),
+			json!({ "event": "split", "split": 2, "name": "1-3", "score": 1500, "total": 4000, "delta": -250 })
+		);
+		assert_eq!(event.to_string(), "Split 1-3: 1500 (total 4000, -250)");
+		assert_eq!(
+			serde_json::to_value(RunEvent::HookStatus { connected: true }).unwrap(),
+			json!({ "event": "hook_status", "connected": true })
+		);
+	}
+}
@@ -0,0 +1,197 @@
+use eframe::egui::{
+	CentralPanel, CollapsingHeader, ComboBox, Context, Grid, Id, RichText, ScrollArea, TextEdit, Ui, ViewportBuilder,
+	ViewportId,
+};
+
+use crate::{
+	Category, CategoryManager, ZeroError,
+	database::HistoryRun,
+	split_name,
+	stats::cached,
+	theme::{DARK_ORANGE, GREEN, LIGHT_ORANGE},
+	worker::DbWorker,
+};
+
+/// Bumped whenever a tag or note is saved, so cached queries that depend on them run again
+const REVISION_ID: &str = "history_revision";
+
+const ERROR_ID: &str = "history_error";
+
+/// Window listing the runs of the current category, where runs can be tagged and splits given notes
+pub fn history_window(ctx: &Context, db: &DbWorker, categories: &CategoryManager, open: &mut bool) {
+	ctx.show_viewport_immediate(
+		ViewportId::from_hash_of("history_viewport"),
+		ViewportBuilder::default().with_title("History"),
+		|ctx, _| {
+			CentralPanel::default().show(ctx, |ui| {
+				let category = categories.current().clone();
+				let tag = ui
+					.horizontal(|ui| {
+						ui.label(RichText::new(&category.name).color(GREEN).heading());
+						tag_filter(ui, db, categories, Id::new("history_tag"))
+					})
+					.inner;
+
+				if let Some(err) = ctx.data(|data| data.get_temp::<String>(Id::new(ERROR_ID))) {
+					ui.colored_label(DARK_ORANGE, err);
+				}
+
+				let key = (category.id, categories.stats().attempts, revision(ctx), tag.clone());
+				let runs = match cached(ui, Id::new("run_history"), key, || {
+					let category = category.clone();
+					db.request(move |db| Ok(db.get_run_history(&category, tag.as_deref())?))
+				}) {
+					Some(Ok(runs)) => runs,
+					Some(Err(err)) => {
+						ui.colored_label(DARK_ORANGE, format!("Couldn't read runs: {err}"));
+						return;
+					}
+					None => {
+						ui.label("Loading...");
+						return;
+					}
+				};
+				if runs.is_empty() {
+					ui.label("No runs to show");
+					return;
+				}
+
+				ScrollArea::vertical().show(ui, |ui| {
+					for run in &runs {
+						run_entry(ui, db, &category, run);
+					}
+				});
+			});
+
+			if ctx.input(|i| i.viewport().close_requested()) {
+				*open = false
+			};
+		},
+	);
+}
+
+/// One run, opening up to its tags and the notes on each split
+fn run_entry(ui: &mut Ui, db: &DbWorker, category: &Category, run: &HistoryRun) {
+	let mut title = format!("{}  {}", run.datetime.as_deref().unwrap_or("Unknown date"), run.total());
+	if run.imported == Some(true) {
+		title.push_str("  (imported)");
+	}
+	if !run.tags.is_empty() {
+		title.push_str(&format!("  [{}]", run.tags.join(", ")));
+	}
+
+	CollapsingHeader::new(title)
+		.id_salt(("history_run", run.id))
+		.show(ui, |ui| {
+			ui.horizontal(|ui| {
+				ui.label("Tags");
+				let run_id = run.id;
+				edit_field(
+					ui,
+					Id::new(("run_tags", run_id)),
+					&run.tags.join(", "),
+					"practice, new route",
+					|text| {
+						let tags = parse_tags(&text);
+						db.call(move |db| Ok(db.set_run_tags(run_id, &tags)?))
+					},
+				);
+			});
+
+			Grid::new(("history_splits", run.id)).striped(true).show(ui, |ui| {
+				for header in ["Split", "Score", "Note"] {
+					ui.colored_label(GREEN, header);
+				}
+				ui.end_row();
+
+				for split in &run.splits {
+					ui.label(split_name(category.mode, split.split_num as usize, true));
+					if split.final_split == Some(true) {
+						ui.colored_label(LIGHT_ORANGE, split.score.to_string())
+							.on_hover_text("The run ended here");
+					} else {
+						ui.label(split.score.to_string());
+					}
+					let split_id = split.id;
+					edit_field(ui, Id::new(("split_note", split_id)), &split.note, "", |note| {
+						db.call(move |db| Ok(db.set_split_note(split_id, note.trim())?))
+					});
+					ui.end_row();
+				}
+			});
+		});
+}
+
+/// Text field that saves when it loses focus, if its text changed
+fn edit_field(ui: &mut Ui, id: Id, saved: &str, hint: &str, save: impl FnOnce(String) -> Result<(), ZeroError>) {
+	let ctx = ui.ctx().clone();
+	let mut text = ctx
+		.data(|data| data.get_temp::<String>(id))
+		.unwrap_or_else(|| saved.to_owned());
+	let response = ui.add(TextEdit::singleline(&mut text).hint_text(hint).desired_width(240.0));
+
+	if response.lost_focus() {
+		ctx.data_mut(|data| data.remove::<String>(id));
+		if text != saved {
+			match save(text) {
+				Ok(()) => ctx.data_mut(|data| {
+					data.remove::<String>(Id::new(ERROR_ID));
+					let revision = data.get_temp::<u64>(Id::new(REVISION_ID)).unwrap_or(0);
+					data.insert_temp(Id::new(REVISION_ID), revision + 1);
+				}),
+				Err(err) => ctx.data_mut(|data| data.insert_temp(Id::new(ERROR_ID), format!("Couldn't save: {err:?}"))),
+			}
+		}
+	} else if response.changed() {
+		ctx.data_mut(|data| data.insert_temp(id, text));
+	}
+}
+
+/// Changes each time a tag or note is saved
+pub fn revision(ctx: &Context) -> u64 {
+	ctx.data(|data| data.get_temp(Id::new(REVISION_ID))).unwrap_or(0)
+}
+
+/// Tag picker for the current category's runs, `None` meaning every run.
+/// Shows nothing when no run of the category is tagged.
+pub fn tag_filter(ui: &mut Ui, db: &DbWorker, categories: &CategoryManager, id: Id) -> Option<String> {
+	let category = categories.current().clone();
+	let key = (category.id, revision(ui.ctx()));
+	let tags = cached(ui, id.with("tags"), key, || {
+		db.request(move |db| Ok(db.get_category_tags(&category)?))
+	})
+	.and_then(Result::ok)
+	.unwrap_or_default();
+	if tags.is_empty() {
+		return None;
+	}
+
+	let ctx = ui.ctx().clone();
+	// A tag from another category, or one that's been removed, picks every run
+	let mut selected = ctx
+		.data(|data| data.get_temp::<Option<String>>(id))
+		.flatten()
+		.filter(|tag| tags.contains(tag));
+	ui.label("Tag");
+	ComboBox::from_id_salt(id)
+		.selected_text(selected.as_deref().unwrap_or("Any"))
+		.show_ui(ui, |ui| {
+			ui.selectable_value(&mut selected, None, "Any");
+			for tag in tags {
+				ui.selectable_value(&mut selected, Some(tag.clone()), tag);
+			}
+		});
+	ctx.data_mut(|data| data.insert_temp(id, selected.clone()));
+	selected
+}
+
+/// Comma separated tags, trimmed, without empty or repeated ones
+fn parse_tags(text: &str) -> Vec<String> {
+	let mut tags = Vec::new();
+	for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+		if !tags.iter().any(|t| t == tag) {
+			tags.push(tag.to_owned());
+		}
+	}
+	tags
+}
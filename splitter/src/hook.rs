@@ -9,6 +9,7 @@ use std::{
 
 use crate::system::{self, ProcessHandle};
 use bytemuck::cast_slice;
+use log::{debug, info};
 use windows::{
 	Win32::System::{
 		LibraryLoader::{GetModuleHandleA, GetProcAddress},
@@ -39,7 +40,8 @@ pub fn hook_zeroranger() {
 }
 
 fn find_zeroranger() -> Option<ProcessHandle> {
-	println!("Searching for process to hook...");
+	// Runs every second while the game isn't open
+	debug!("Searching for process to hook...");
 	for pid in system::list_processes().unwrap() {
 		if let Ok(handle) = system::open_process(pid, PROCESS_QUERY_LIMITED_INFORMATION) {
 			let exe = handle.get_executable();
@@ -53,7 +55,7 @@ fn find_zeroranger() -> Option<ProcessHandle> {
 						| PROCESS_QUERY_INFORMATION,
 				)
 				.expect("Acquiring privileged handle");
-				println!("Found ZeroRanger with PID {}", pid);
+				info!("Found ZeroRanger with PID {}", pid);
 
 				return Some(privileged_handle);
 			}
@@ -0,0 +1,141 @@
+use crate::{Gamemode, ZeroError};
+
+/// How the scores typed into the importer should be read
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+	/// Score gained during each split
+	#[default]
+	Relative,
+	/// Total score at the end of each split
+	RunningTotal,
+}
+
+/// A run read from the importer, ready to be previewed and saved
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedRun {
+	/// The mode the scores were read as
+	pub mode: ImportMode,
+	/// Score gained during each split
+	pub splits: Vec<i32>,
+}
+
+impl ParsedRun {
+	pub fn totals(&self) -> Vec<i32> {
+		self.splits
+			.iter()
+			.scan(0, |total, &s| {
+				*total += s;
+				Some(*total)
+			})
+			.collect()
+	}
+
+	/// Whether these per-split scores go up at every split, the way running totals do.
+	/// Scores per split usually rise too, so this is only a hint and never changes how they're read.
+	pub fn looks_like_totals(&self) -> bool {
+		self.mode == ImportMode::Relative && self.splits.len() > 1 && self.splits.is_sorted_by(|a, b| a < b)
+	}
+}
+
+/// Read a list of scores separated by anything that isn't part of a number:
+/// commas, spaces, tabs, new lines, semicolons...
+/// Digits can be grouped with `_` or `'` inside a score, so `1_000_500` and `12'345` are one score each.
+pub fn parse_run(input: &str, mode: ImportMode, gamemode: Gamemode) -> Result<ParsedRun, ZeroError> {
+	let scores = split_scores(input)
+		.iter()
+		.enumerate()
+		.map(|(idx, s)| {
+			s.parse::<i32>()
+				.map_err(|_| ZeroError::ImportError(format!("Split {}: \"{s}\" is not a whole number", idx + 1)))
+		})
+		.collect::<Result<Vec<i32>, ZeroError>>()?;
+
+	if scores.is_empty() {
+		return Err(ZeroError::ImportError("No scores entered".to_owned()));
+	}
+	if scores.len() > gamemode.splits() {
+		return Err(ZeroError::ImportError(format!(
+			"{} scores entered but {gamemode:?} only has {} splits",
+			scores.len(),
+			gamemode.splits()
+		)));
+	}
+	if let Some(idx) = scores.iter().position(|&s| s < 0) {
+		return Err(ZeroError::ImportError(format!(
+			"Split {}: {} is negative",
+			idx + 1,
+			scores[idx]
+		)));
+	}
+
+	let splits = match mode {
+		ImportMode::RunningTotal => {
+			if let Some(idx) = scores.windows(2).position(|w| w[1] < w[0]) {
+				return Err(ZeroError::ImportError(format!(
+					"Split {}: total {} is lower than the total before it",
+					idx + 2,
+					scores[idx + 1]
+				)));
+			}
+			scores
+				.iter()
+				.scan(0, |prev, &total| {
+					let split = total - *prev;
+					*prev = total;
+					Some(split)
+				})
+				.collect()
+		}
+		ImportMode::Relative => scores,
+	};
+
+	Ok(ParsedRun { mode, splits })
+}
+
+/// The scores in the input, with digit group separators taken out
+fn split_scores(input: &str) -> Vec<String> {
+	let part_of_number = |c: char| c.is_alphanumeric() || matches!(c, '-' | '+' | '.' | '_' | '\'');
+	input
+		.split(|c: char| !part_of_number(c))
+		.filter(|word| !word.is_empty())
+		.map(|word| word.replace(['_', '\''], ""))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn running_totals() -> Result<(), ZeroError> {
+		let run = parse_run("100, 300\t600\n1000", ImportMode::RunningTotal, Gamemode::GreenOrange)?;
+		assert_eq!(run.splits, vec![100, 200, 300, 400]);
+
+		// Rising scores are still read per split, with a hint that they might be totals
+		let run = parse_run("100, 300\t600\n1000", ImportMode::default(), Gamemode::GreenOrange)?;
+		assert_eq!(run.splits, vec![100, 300, 600, 1000]);
+		assert!(run.looks_like_totals());
+
+		let run = parse_run("100;50;300", ImportMode::Relative, Gamemode::GreenOrange)?;
+		assert!(!run.looks_like_totals());
+		assert_eq!(run.totals(), vec![100, 150, 450]);
+		assert!(!parse_run("100;100", ImportMode::Relative, Gamemode::GreenOrange)?.looks_like_totals());
+
+		assert!(parse_run("100, 2OO", ImportMode::Relative, Gamemode::GreenOrange).is_err());
+		assert!(parse_run("300, 100", ImportMode::RunningTotal, Gamemode::GreenOrange).is_err());
+		assert!(parse_run("1 2 3 4 5 6 7 8 9", ImportMode::Relative, Gamemode::GreenOrange).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn separators() -> Result<(), ZeroError> {
+		let run = parse_run("100 200 300", ImportMode::Relative, Gamemode::GreenOrange)?;
+		assert_eq!(run.splits, vec![100, 200, 300]);
+		let run = parse_run("100,200,300", ImportMode::Relative, Gamemode::GreenOrange)?;
+		assert_eq!(run.splits, vec![100, 200, 300]);
+
+		let run = parse_run("1_000_500, 12'345\n2000", ImportMode::Relative, Gamemode::GreenOrange)?;
+		assert_eq!(run.splits, vec![1000500, 12345, 2000]);
+		Ok(())
+	}
+}
@@ -0,0 +1,136 @@
+use std::{fmt, path::PathBuf};
+
+use crate::{ZeroError, backup::BACKUP_DIR, database::Database, profile};
+
+/// Something wrong with the saved runs, found by [`Database::check_integrity`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+	pub kind: IssueKind,
+	/// Runs with the issue, empty when it isn't about runs
+	pub runs: Vec<i64>,
+	/// Number of rows with the issue
+	pub rows: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+	/// Runs of a category that was deleted
+	OrphanRuns,
+	/// Splits of a run that doesn't exist
+	OrphanSplits,
+	/// Runs without a single split
+	EmptyRuns,
+	/// The same split saved more than once in a run
+	DuplicateSplits,
+	/// Runs that end on more than one split
+	MultipleFinals,
+	/// More splits than the category's mode has
+	TooManySplits,
+	/// Split numbers outside the category's mode, e.g. a White Vanilla run in a Green Orange category
+	ModeMismatch,
+	/// Tags, notes and goals left behind by deleted runs, splits and categories
+	OrphanRows,
+}
+
+impl IssueKind {
+	/// Whether [`Database::repair_integrity`] fixes it. The others need a person to decide what the run should be.
+	pub fn repairable(self) -> bool {
+		!matches!(self, IssueKind::TooManySplits | IssueKind::ModeMismatch)
+	}
+
+	fn description(self) -> &'static str {
+		match self {
+			IssueKind::OrphanRuns => "runs of a deleted category",
+			IssueKind::OrphanSplits => "splits of a missing run",
+			IssueKind::EmptyRuns => "runs without splits",
+			IssueKind::DuplicateSplits => "extra copies of a split",
+			IssueKind::MultipleFinals => "splits marked as the end of a run that went further",
+			IssueKind::TooManySplits => "runs with more splits than their mode has",
+			IssueKind::ModeMismatch => "runs with split numbers from another mode",
+			IssueKind::OrphanRows => "tags, notes or goals left over from deleted data",
+		}
+	}
+
+	fn repair(self) -> &'static str {
+		match self {
+			IssueKind::OrphanRuns | IssueKind::OrphanSplits | IssueKind::EmptyRuns | IssueKind::OrphanRows => "removed",
+			IssueKind::DuplicateSplits => "the first copy is kept",
+			IssueKind::MultipleFinals => "only the last split stays the end",
+			IssueKind::TooManySplits | IssueKind::ModeMismatch => "not repaired, check these runs by hand",
+		}
+	}
+}
+
+impl fmt::Display for Issue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {}", self.rows, self.kind.description())?;
+		if !self.runs.is_empty() {
+			// Long lists don't help, the first few are enough to go look
+			let shown = self.runs.iter().take(5).map(|id| id.to_string()).collect::<Vec<_>>();
+			let more = if self.runs.len() > shown.len() { ", ..." } else { "" };
+			write!(f, " (run {}{more})", shown.join(", "))?;
+		}
+		write!(f, ": {}", self.kind.repair())
+	}
+}
+
+/// What a repair changed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RepairSummary {
+	pub runs_removed: usize,
+	pub splits_removed: usize,
+	pub finals_cleared: usize,
+	pub other_rows_removed: usize,
+}
+
+/// Back up the database, then repair it. Returns where the backup is.
+pub fn repair(db: &Database) -> Result<(PathBuf, RepairSummary), ZeroError> {
+	let backup = db.backup_into(&profile::path(BACKUP_DIR), "pre-repair")?;
+	Ok((backup, db.repair_integrity()?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::database::SplitRecord;
+
+	#[test]
+	fn check_and_repair() -> Result<(), ZeroError> {
+		let db = Database::init()?;
+		let kinds = |db: &Database| -> Result<Vec<IssueKind>, ZeroError> {
+			Ok(db.check_integrity()?.iter().map(|issue| issue.kind).collect())
+		};
+		db.import_run(vec![10, 20], &"default".to_string())?;
+		assert!(kinds(&db)?.is_empty());
+
+		// the split run 1 ended on
+		let ends_on = |split_num| SplitRecord {
+			final_split: Some(true),
+			..SplitRecord::new(1, split_num, 100)
+		};
+		// second split saved twice
+		db.import_split_records("default", &[SplitRecord::new(1, 0, 100), ends_on(1), ends_on(1)])?;
+		// ending on both split 0 and split 1
+		db.import_split_records("default", &[ends_on(0), ends_on(1)])?;
+		// split 12 doesn't exist in Green Orange
+		db.import_split_records("default", &[SplitRecord::new(1, 0, 100), ends_on(12)])?;
+		db.import_run(vec![], &"default".to_string())?;
+		assert_eq!(
+			kinds(&db)?,
+			vec![
+				IssueKind::EmptyRuns,
+				IssueKind::DuplicateSplits,
+				IssueKind::MultipleFinals,
+				IssueKind::ModeMismatch
+			]
+		);
+
+		let summary = db.repair_integrity()?;
+		assert_eq!(summary.runs_removed, 1);
+		assert_eq!(summary.splits_removed, 1);
+		assert_eq!(summary.finals_cleared, 1);
+		// the mode mismatch is left for the user
+		assert_eq!(kinds(&db)?, vec![IssueKind::ModeMismatch]);
+		Ok(())
+	}
+}
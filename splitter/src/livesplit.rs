@@ -0,0 +1,200 @@
+use std::{
+	io::Write,
+	net::{TcpStream, ToSocketAddrs},
+	sync::mpsc::Receiver,
+	thread,
+	time::Duration,
+};
+
+use log::{debug, info, warn};
+
+use crate::{ZeroError, events::RunEvent};
+
+/// How long to wait for LiveSplit to accept the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Drive a LiveSplit timer through its server component at `address`, from a background thread.
+/// LiveSplit doesn't need to be running yet, the connection is made when there's something to send
+/// and made again if LiveSplit is restarted.
+pub fn start(address: String, events: Receiver<RunEvent>) -> Result<(), ZeroError> {
+	thread::Builder::new().name("livesplit".to_owned()).spawn(move || {
+		let mut bridge = Bridge {
+			address,
+			connection: None,
+			warned: false,
+		};
+		for event in events {
+			let commands = commands(&event);
+			if !commands.is_empty() {
+				bridge.send(&commands);
+			}
+		}
+	})?;
+	Ok(())
+}
+
+/// The LiveSplit Server commands for an event
+fn commands(event: &RunEvent) -> Vec<String> {
+	match event {
+		RunEvent::Started { split, comparison, .. } => {
+			let mut commands = vec!["reset".to_owned()];
+			if let Some(comparison) = livesplit_comparison(comparison) {
+				commands.push(format!("setcomparison {comparison}"));
+			}
+			commands.push("starttimer".to_owned());
+			// White Vanilla runs can start on a later stage, skip LiveSplit to the same split
+			commands.extend((0..*split).map(|_| "skipsplit".to_owned()));
+			commands
+		}
+		RunEvent::Split { .. } => vec!["split".to_owned()],
+		// The last split has no Split event, splitting it finishes LiveSplit's run
+		RunEvent::Ended { cleared: true, .. } => vec!["split".to_owned()],
+		RunEvent::Ended { .. } => vec!["reset".to_owned()],
+		_ => Vec::new(),
+	}
+}
+
+/// LiveSplit's name for one of our comparisons, if it has the same one
+fn livesplit_comparison(name: &str) -> Option<&'static str> {
+	match name {
+		"Personal Best" => Some("Personal Best"),
+		"Average" => Some("Average Segments"),
+		"Median" => Some("Median Segments"),
+		"Latest Run" => Some("Latest Run"),
+		_ => None,
+	}
+}
+
+struct Bridge {
+	address: String,
+	connection: Option<TcpStream>,
+	/// Only warn once about LiveSplit not being there, until it's back
+	warned: bool,
+}
+
+impl Bridge {
+	fn send(&mut self, commands: &[String]) {
+		// A second try in case LiveSplit was restarted since the last command
+		for _ in 0..2 {
+			let Some(stream) = self.connect() else {
+				return;
+			};
+			let sent = commands
+				.iter()
+				.try_for_each(|command| stream.write_all(format!("{command}\r\n").as_bytes()));
+			match sent {
+				Ok(()) => {
+					debug!("Sent {commands:?} to LiveSplit");
+					return;
+				}
+				Err(err) => {
+					debug!("Lost LiveSplit connection: {err}");
+					self.connection = None;
+				}
+			}
+		}
+	}
+
+	fn connect(&mut self) -> Option<&mut TcpStream> {
+		if self.connection.is_none() {
+			let stream = self
+				.address
+				.to_socket_addrs()
+				.ok()
+				.and_then(|mut addrs| addrs.next())
+				.ok_or_else(|| format!("can't resolve {}", self.address))
+				.and_then(|addr| TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).map_err(|err| err.to_string()));
+			match stream {
+				Ok(stream) => {
+					info!("Connected to LiveSplit Server at {}", self.address);
+					self.warned = false;
+					self.connection = Some(stream);
+				}
+				Err(err) if !self.warned => {
+					warn!("Couldn't connect to LiveSplit Server at {}: {err}", self.address);
+					self.warned = true;
+				}
+				Err(_) => {}
+			}
+		}
+		self.connection.as_mut()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		io::{BufRead, BufReader},
+		net::TcpListener,
+		sync::mpsc,
+	};
+
+	use super::*;
+
+	#[test]
+	fn sends_commands() -> Result<(), ZeroError> {
+		// Stands in for LiveSplit
+		let server = TcpListener::bind("127.0.0.1:0")?;
+		let (tx, rx) = mpsc::channel();
+		start(server.local_addr()?.to_string(), rx)?;
+
+		tx.send(RunEvent::Started {
+			category: "WV".to_owned(),
+			split: 2,
+			name: "1-3".to_owned(),
+			comparison: "Average".to_owned(),
+		})
+		.unwrap();
+		tx.send(RunEvent::HookStatus { connected: true }).unwrap();
+		tx.send(RunEvent::Split {
+			split: 2,
+			name: "1-3".to_owned(),
+			score: 100,
+			total: 100,
+			delta: None,
+		})
+		.unwrap();
+		tx.send(RunEvent::Ended {
+			total: 100,
+			split: 3,
+			pb: false,
+			cleared: false,
+		})
+		.unwrap();
+		// a run that's cleared isn't reset
+		tx.send(RunEvent::Started {
+			category: "GO".to_owned(),
+			split: 0,
+			name: "1-1".to_owned(),
+			comparison: "Goal: 1M".to_owned(),
+		})
+		.unwrap();
+		tx.send(RunEvent::Ended {
+			total: 5000,
+			split: 7,
+			pb: true,
+			cleared: true,
+		})
+		.unwrap();
+		drop(tx);
+
+		let (stream, _) = server.accept()?;
+		let lines = BufReader::new(stream).lines().collect::<Result<Vec<_>, _>>()?;
+		assert_eq!(
+			lines,
+			[
+				"reset",
+				"setcomparison Average Segments",
+				"starttimer",
+				"skipsplit",
+				"skipsplit",
+				"split",
+				"reset",
+				"reset",
+				"starttimer",
+				"split"
+			]
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,198 @@
+use std::{
+	collections::VecDeque,
+	env,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	panic,
+	path::PathBuf,
+	str::FromStr,
+	sync::{Mutex, OnceLock},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::{ZeroError, profile};
+
+/// Name of the log file in the profile's folder
+pub const LOG_FILE: &str = "zerosplitter.log";
+
+/// The log file is rotated once it's this big
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Old log files kept next to the current one, as `zerosplitter.log.1` (newest) and up
+const OLD_LOGS_KEPT: usize = 3;
+
+/// Lines kept in memory for the log viewer
+const RECENT_LINES: usize = 1000;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+struct Logger {
+	path: PathBuf,
+	file: Mutex<Option<LogFile>>,
+	recent: Mutex<VecDeque<String>>,
+	/// Set when `RUST_LOG` picks the level, which then wins over the config
+	level_from_env: bool,
+}
+
+struct LogFile {
+	file: File,
+	size: u64,
+}
+
+/// Send every `log` message to the profile's log file, and to stderr for anyone running from a terminal.
+/// The level is `RUST_LOG` if it's set, info until the config is loaded otherwise.
+pub fn init() -> Result<(), ZeroError> {
+	let env_level = env::var("RUST_LOG")
+		.ok()
+		.and_then(|level| LevelFilter::from_str(&level).ok());
+	let path = profile::path(LOG_FILE);
+	let file = open(&path)?;
+
+	let logger = Logger {
+		path,
+		file: Mutex::new(Some(file)),
+		recent: Mutex::new(VecDeque::with_capacity(RECENT_LINES)),
+		level_from_env: env_level.is_some(),
+	};
+	LOGGER.set(logger).map_err(|_| ZeroError::StaticAlreadyInit)?;
+	log::set_logger(LOGGER.get().unwrap()).map_err(|_| ZeroError::StaticAlreadyInit)?;
+	log::set_max_level(env_level.unwrap_or(LevelFilter::Info));
+
+	// Without a console, a panic message would be lost
+	let default_hook = panic::take_hook();
+	panic::set_hook(Box::new(move |info| {
+		log::error!("{info}");
+		log::logger().flush();
+		default_hook(info);
+	}));
+	Ok(())
+}
+
+/// Change the level, unless `RUST_LOG` set it
+pub fn set_level(level: LevelFilter) {
+	if LOGGER.get().is_some_and(|logger| !logger.level_from_env) {
+		log::set_max_level(level);
+	}
+}
+
+/// Where the log is written, if logging has started
+pub fn log_path() -> Option<PathBuf> {
+	LOGGER.get().map(|logger| logger.path.clone())
+}
+
+/// The last lines logged, oldest first
+pub fn recent_lines() -> Vec<String> {
+	LOGGER
+		.get()
+		.map(|logger| logger.recent.lock().unwrap().iter().cloned().collect())
+		.unwrap_or_default()
+}
+
+fn open(path: &PathBuf) -> Result<LogFile, io::Error> {
+	let file = OpenOptions::new().create(true).append(true).open(path)?;
+	let size = file.metadata()?.len();
+	Ok(LogFile { file, size })
+}
+
+/// Shift `zerosplitter.log.N` to `.N+1`, dropping the oldest, and start a new log
+fn rotate(path: &PathBuf) -> Result<LogFile, io::Error> {
+	let old = |n: usize| PathBuf::from(format!("{}.{n}", path.display()));
+	let _ = fs::remove_file(old(OLD_LOGS_KEPT));
+	for n in (1..OLD_LOGS_KEPT).rev() {
+		let _ = fs::rename(old(n), old(n + 1));
+	}
+	fs::rename(path, old(1))?;
+	open(path)
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= log::max_level()
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+		let line = format!(
+			"{} {:<5} {}: {}",
+			timestamp(SystemTime::now()),
+			record.level(),
+			record.target(),
+			record.args()
+		);
+		let _ = writeln!(io::stderr(), "{line}");
+
+		if let Ok(mut file) = self.file.lock() {
+			if file.as_ref().is_some_and(|f| f.size > MAX_LOG_BYTES) {
+				// If rotating fails keep going without a file rather than failing every log call
+				*file = rotate(&self.path).ok();
+			}
+			if let Some(f) = file.as_mut()
+				&& writeln!(f.file, "{line}").is_ok()
+			{
+				f.size += line.len() as u64 + 1;
+			}
+		}
+
+		let mut recent = self.recent.lock().unwrap();
+		if recent.len() == RECENT_LINES {
+			recent.pop_front();
+		}
+		recent.push_back(line);
+	}
+
+	fn flush(&self) {
+		if let Ok(mut file) = self.file.lock()
+			&& let Some(f) = file.as_mut()
+		{
+			let _ = f.file.flush();
+		}
+	}
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC
+fn timestamp(time: SystemTime) -> String {
+	let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+	let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+	// Civil date from days since 1970-01-01, from Howard Hinnant's date algorithms
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z.rem_euclid(146097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+
+	format!(
+		"{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+		secs / 3600,
+		secs / 60 % 60,
+		secs % 60
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn timestamps() {
+		assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01 00:00:00");
+		assert_eq!(
+			timestamp(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+			"2000-02-29 12:34:56"
+		);
+		assert_eq!(
+			timestamp(UNIX_EPOCH + Duration::from_secs(1_798_761_599)),
+			"2026-12-31 23:59:59"
+		);
+	}
+}
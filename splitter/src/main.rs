@@ -1,55 +1,125 @@
+// No console window in release builds, logs go to the log file instead
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
 use std::{
 	env,
 	net::UdpSocket,
+	process,
 	sync::{
 		OnceLock,
 		mpsc::{self, Receiver, Sender},
 	},
 	thread,
-	time::Duration,
+	time::{Duration, Instant},
 };
 
 use common::FrameData;
 use eframe::{
-	NativeOptions,
+	App, NativeOptions,
 	egui::{Context, IconData, ThemePreference, ViewportBuilder},
 };
-use log::{debug, error};
+use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 
-use crate::{app::Toggles, config::CONFIG, database::Database, run::Run, theme::zeroranger_visuals};
+use crate::{
+	api::{ApiServer, LiveState},
+	app::Toggles,
+	backup::RecoveryApp,
+	comparison::{Comparison, Goal},
+	config::CONFIG,
+	database::Database,
+	events::RunEvent,
+	run::Run,
+	text_output::TextOutput,
+	theme::zeroranger_visuals,
+	worker::{DbWorker, Pending},
+};
 
+mod api;
 mod app;
+mod archive;
+mod backup;
+mod cli;
+mod comparison;
 mod config;
+mod csv_io;
+mod daemon;
 mod database;
+mod events;
+mod history;
+#[cfg(windows)]
 mod hook;
+mod importer;
+mod integrity;
+mod livesplit;
+mod logger;
+mod profile;
+mod ranks;
 mod run;
+mod stats;
+#[cfg_attr(not(windows), path = "system_fallback.rs")]
 mod system;
+mod text_output;
 mod theme;
 mod ui;
 mod update;
+mod webhooks;
+mod worker;
 
 const SPLIT_DELAY_FRAMES: u32 = 20;
 
+/// The game counts as gone after this long without a frame
+const HOOK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often to check on the database while it's busy
+const DB_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 static EGUI_CTX: OnceLock<Context> = OnceLock::new();
 
 fn main() {
-	config::load_config().unwrap();
-	let zoom_level = CONFIG.get().unwrap().zoom_level;
 	#[cfg(debug_assertions)]
 	unsafe {
 		env::set_var("RUST_BACKTRACE", "1");
 	}
 
-	pretty_env_logger::init();
+	let args = env::args().collect::<Vec<String>>();
+	if let Err(err) = profile::init(&args) {
+		startup_error(&args, format!("Couldn't open the profile: {err:?}"));
+	}
+	if let Err(err) = logger::init() {
+		startup_error(&args, format!("Couldn't open the log file: {err:?}"));
+	}
+	let profile = profile::current();
+	info!("Using profile {} in {}", profile.name, profile.dir().display());
+
+	if let Some(result) = cli::run(&args) {
+		if let Err(err) = result {
+			error!("{err:?}");
+			process::exit(1);
+		}
+		return;
+	}
+	if let Err(err) = profile::remember_current() {
+		error!("Couldn't remember the profile for next time: {err:?}");
+	}
+	if let Err(err) = config::load_config() {
+		startup_error(&args, format!("Couldn't load config.toml: {err:?}"));
+	}
+	logger::set_level(CONFIG.get().unwrap().log_level);
+	let zoom_level = CONFIG.get().unwrap().zoom_level;
+	let title = if profile.name == profile::DEFAULT_PROFILE {
+		"ZeroSplitter".to_owned()
+	} else {
+		format!("ZeroSplitter - {}", profile.name)
+	};
 
 	let options = NativeOptions {
 		viewport: ViewportBuilder::default()
 			.with_inner_size([300.0 * zoom_level, 300.0 * zoom_level])
 			.with_icon(IconData::default())
-			.with_title("ZeroSplitter"),
+			.with_title(title),
 
 		..Default::default()
 	};
@@ -58,7 +128,7 @@ fn main() {
 
 	thread::spawn(|| ipc_thread(tx));
 
-	eframe::run_native(
+	let result = eframe::run_native(
 		"ZeroSplitter",
 		options,
 		Box::new(|c| {
@@ -66,14 +136,50 @@ fn main() {
 			c.egui_ctx.set_theme(ThemePreference::Dark);
 			c.egui_ctx.set_visuals(zeroranger_visuals());
 			c.egui_ctx.set_zoom_factor(CONFIG.get().unwrap().zoom_level);
-			Ok(Box::new(ZeroSplitter::load(rx)))
+			let app: Box<dyn App> = match open_database() {
+				Ok((db, categories)) => Box::new(ZeroSplitter::new(rx, db, categories)),
+				Err(err) => Box::new(RecoveryApp::new(rx, err)),
+			};
+			Ok(app)
 		}),
-	)
-	.unwrap();
+	);
+	if let Err(err) = result {
+		startup_error(&args, format!("Couldn't open the window: {err}"));
+	}
+}
+
+/// Report an error that stops the program from starting, then exit. Release builds have no console,
+/// so outside of commands it's also shown in a message box.
+fn startup_error(args: &[String], message: String) -> ! {
+	system::attach_console();
+	if logger::log_path().is_some() {
+		// The logger prints to stderr as well
+		error!("{message}");
+	} else {
+		eprintln!("{message}");
+	}
+	if !cli::has_command(args) {
+		system::show_error("ZeroSplitter", &message);
+	}
+	process::exit(1);
+}
+
+/// Start the database worker and read the categories
+fn open_database() -> Result<(DbWorker, CategoryManager), ZeroError> {
+	let db = DbWorker::spawn()?;
+	let categories = CategoryManager::open(&db)?;
+	Ok((db, categories))
 }
 
 fn ipc_thread(channel: Sender<FrameData>) {
-	let socket = UdpSocket::bind("127.0.0.1:23888").expect("Binding socket");
+	// When switching profiles the old window may still be closing and holding the port
+	let socket = (0..10)
+		.find_map(|_| {
+			UdpSocket::bind("127.0.0.1:23888")
+				.inspect_err(|_| thread::sleep(Duration::from_millis(500)))
+				.ok()
+		})
+		.expect("Binding socket");
 	socket
 		.set_read_timeout(Some(Duration::from_secs(1)))
 		.expect("Setting socket timeout");
@@ -88,6 +194,7 @@ fn ipc_thread(channel: Sender<FrameData>) {
 			}
 		}
 		// timed out, hook the game
+		#[cfg(windows)]
 		hook::hook_zeroranger();
 	}
 }
@@ -96,6 +203,8 @@ struct ZeroSplitter {
 	categories: CategoryManager,
 	data_source: Receiver<FrameData>,
 	last_frame: FrameData,
+	/// When the last frame came in, `None` until the game is hooked or once it's gone
+	last_frame_at: Option<Instant>,
 	run: Run,
 	waiting_for_category: bool,
 	waiting_for_rename: bool,
@@ -104,17 +213,25 @@ struct ZeroSplitter {
 	dialog_tx: Sender<Option<EntryDialogData>>,
 	split_delay: Option<u32>,
 	start_delay: Option<u32>,
-	db: Database,
+	db: DbWorker,
+	/// Runs that have been sent to the database but not written yet
+	saving: Vec<Pending<(i64, CategoryStats)>>,
+	save_error: Option<String>,
+	/// Everything following along with the run, see [`Self::subscribe`]
+	subscribers: Vec<Sender<RunEvent>>,
+	api: Option<ApiServer>,
+	text_output: Option<TextOutput>,
 	toggles: Toggles,
 }
 
 impl ZeroSplitter {
-	fn new(data_source: Receiver<FrameData>, db: Database) -> Self {
+	fn new(data_source: Receiver<FrameData>, db: DbWorker, categories: CategoryManager) -> Self {
 		let (tx, rx) = mpsc::channel();
 		let mut zerosplitter = Self {
-			categories: CategoryManager::init(),
+			categories,
 			data_source,
 			last_frame: FrameData::default(),
+			last_frame_at: None,
 			run: Run::Inactive,
 			dialog_rx: rx,
 			dialog_tx: tx,
@@ -124,31 +241,212 @@ impl ZeroSplitter {
 			split_delay: None,
 			start_delay: None,
 			db,
+			saving: Vec::new(),
+			save_error: None,
+			subscribers: Vec::new(),
+			api: None,
+			text_output: None,
 			toggles: Default::default(),
 		};
 
-		zerosplitter.categories.load(&zerosplitter.db).unwrap();
+		let config = CONFIG.get().unwrap();
+		if config.http_server {
+			let events = zerosplitter.subscribe();
+			zerosplitter.api = ApiServer::start(config.http_port, events)
+				.inspect_err(|err| error!("Couldn't start HTTP server: {err:?}"))
+				.ok();
+		}
+		if let Some(dir) = &config.text_output_dir {
+			zerosplitter.text_output = TextOutput::start(dir.clone())
+				.inspect_err(|err| error!("Couldn't write text files to {}: {err:?}", dir.display()))
+				.ok();
+		}
+		if !config.webhooks.is_empty() {
+			let events = zerosplitter.subscribe();
+			if let Err(err) = webhooks::start(config.webhooks.clone(), events) {
+				error!("Couldn't start sending webhooks: {err:?}");
+			}
+		}
+		if config.livesplit_server {
+			let events = zerosplitter.subscribe();
+			if let Err(err) = livesplit::start(config.livesplit_address.clone(), events) {
+				error!("Couldn't start LiveSplit connection: {err:?}");
+			}
+		}
+		// Nothing waits on this, errors are logged
+		zerosplitter.db.request(|db| {
+			scheduled_backup(db);
+			Ok(())
+		});
 
 		zerosplitter
 	}
 
-	fn load(data_source: Receiver<FrameData>) -> Self {
-		let db = Database::init().unwrap();
+	/// Hand the current state to the integrations that serve it
+	fn publish(&self) {
+		if self.api.is_none() && self.text_output.is_none() {
+			return;
+		}
+		let state = LiveState::new(self);
+		if let Some(text_output) = &self.text_output {
+			text_output.publish(state.clone());
+		}
+		if let Some(api) = &self.api {
+			api.publish(state);
+		}
+	}
 
-		Self::new(data_source, db.clone())
+	/// Get every [`RunEvent`] from now on. Dropping the receiver unsubscribes.
+	fn subscribe(&mut self) -> Receiver<RunEvent> {
+		let (tx, rx) = mpsc::channel();
+		self.subscribers.push(tx);
+		rx
+	}
+
+	fn emit(&mut self, event: RunEvent) {
+		self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
 	}
 
+	fn emit_started(&mut self) {
+		let category = self.categories.current();
+		let split = self.run.current_split().unwrap_or(0);
+		let event = RunEvent::Started {
+			category: category.name.clone(),
+			split,
+			name: split_name(category.mode, split, true),
+			comparison: self.categories.comparison().name(),
+		};
+		self.emit(event);
+	}
+
+	/// Finish the current split and move on to the next one
+	fn split(&mut self) {
+		let split = self.run.current_split().unwrap();
+		let score = self.run.scores().unwrap().get(split).copied().unwrap_or(0);
+		let total = self.run.score().unwrap_or(0);
+		self.run.split().unwrap();
+
+		let name = split_name(self.categories.current().mode, split, true);
+		let comparison = &self.categories.comparison_cache;
+		let delta = (!comparison.is_empty()).then(|| total - comparison.iter().take(split + 1).sum::<i32>());
+		self.emit(RunEvent::Split {
+			split,
+			name: name.clone(),
+			score,
+			total,
+			delta,
+		});
+		// Without an earlier score on the split there's nothing to beat
+		if let Some(&previous) = self.categories.stats().gold_splits.get(split)
+			&& previous > 0
+			&& score > previous
+		{
+			self.emit(RunEvent::Gold {
+				split,
+				name,
+				score,
+				previous,
+			});
+		}
+	}
+
+	/// Switch to another category, ending the current run
+	fn select_category(&mut self, idx: usize) -> Result<(), ZeroError> {
+		self.end_run();
+		if self.categories.set_current(idx, &self.db)? {
+			let category = self.categories.current();
+			let event = RunEvent::CategoryChanged {
+				category: category.name.clone(),
+				mode: category.mode,
+			};
+			self.emit(event);
+		}
+		Ok(())
+	}
+
+	/// Notice the game going away, as no more frames come in to say so
+	fn check_hook(&mut self) {
+		if self.last_frame_at.is_some_and(|at| at.elapsed() > HOOK_TIMEOUT) {
+			self.last_frame_at = None;
+			self.emit(RunEvent::HookStatus { connected: false });
+		}
+	}
+
+	/// Write the run to the database in the background. The category stats are
+	/// updated once it's written, see [`Self::poll_saves`].
 	fn save_splits(&mut self) {
 		if self.run.is_active() && self.run.scores().unwrap().iter().sum::<i32>() > 0 {
 			debug!("Saving splits");
 
-			if let Err(err) = self.db.insert_run(&self.categories, &self.run) {
-				error!("Error writing run to database: {err}");
+			let category = self.categories.current().clone();
+			let run = self.run.clone();
+			self.saving.push(self.db.request(move |db| {
+				db.insert_run(&category, &run)?;
+				scheduled_backup(db);
+				Ok((category.id, CategoryStats::load(db, &category)?))
+			}));
+
+			let scores = self.run.scores().unwrap();
+			let total = scores.iter().sum::<i32>();
+			let previous = self.categories.stats().pb;
+			self.categories.stats.add_run(&scores);
+			if total > previous {
+				self.emit(RunEvent::PersonalBest { total, previous });
+			}
+			self.emit(RunEvent::Ended {
+				total,
+				split: self.run.current_split().unwrap(),
+				pb: total > previous,
+				cleared: self.run.is_cleared(),
+			});
+		}
+	}
+
+	/// Pick up runs the database has finished writing, and anything the categories were waiting for
+	fn poll_saves(&mut self) {
+		let mut saved = None;
+		let mut failed = false;
+		self.saving.retain(|save| match save.poll() {
+			None => true,
+			Some(Ok(result)) => {
+				saved = Some(result);
+				self.save_error = None;
+				false
+			}
+			Some(Err(err)) => {
+				error!("Error writing run to database: {err:?}");
+				self.save_error = Some(format!("{err:?}"));
+				failed = true;
+				false
+			}
+		});
+		if failed {
+			// The run was already counted in the stats
+			self.categories.refresh_stats(&self.db);
+		} else if let Some((category_id, stats)) = saved
+			// A later run's stats include this one
+			&& self.saving.is_empty()
+			&& category_id == self.categories.current().id
+		{
+			self.categories.stats = stats;
+			self.categories.refresh_comparison(&self.db);
+		}
+		self.categories.poll(&self.db);
+	}
+
+	/// Block until every queued run is written, for when the program is closing
+	fn finish_saves(&mut self) {
+		for save in self.saving.drain(..) {
+			if let Err(err) = save.wait() {
+				error!("Error writing run to database: {err:?}");
 			}
 		}
 	}
 
 	fn update_frame(&mut self, frame: FrameData) {
+		if self.last_frame_at.replace(Instant::now()).is_none() {
+			self.emit(RunEvent::HookStatus { connected: true });
+		}
 		// Difficulty is ZR-speak for gamemode
 		if frame.difficulty == 0 {
 			self.update_greenorange(frame);
@@ -178,18 +476,20 @@ impl ZeroSplitter {
 			self.reset();
 			self.run.start(frame);
 			self.run.set_split(frame_split).unwrap();
-			self.categories.refresh_comparison(&self.db).unwrap();
+			self.categories.refresh_comparison(&self.db);
+			self.emit_started();
 		}
 
 		if !frame.is_menu() && self.run.is_active() {
 			if frame_split >= 8 {
 				// TLB or credits
+				self.run.clear();
 				return;
 			}
 
 			// Split if necessary
 			if (frame_split > self.run.current_split().unwrap()) && !self.last_frame.is_menu() {
-				self.run.split().unwrap();
+				self.split();
 			}
 
 			// Update run and split scores
@@ -226,7 +526,8 @@ impl ZeroSplitter {
 						_ => panic!("Stage out of bounds! {}", frame.stage),
 					})
 					.unwrap();
-				self.categories.refresh_comparison(&self.db).unwrap();
+				self.categories.refresh_comparison(&self.db);
+				self.emit_started();
 				self.start_delay = None;
 				return;
 			}
@@ -242,7 +543,12 @@ impl ZeroSplitter {
 				if split_delay >= 1 {
 					self.split_delay = Some(split_delay - 1)
 				} else {
-					self.run.split().unwrap();
+					if self.run.current_split().unwrap() + 1 == Gamemode::WhiteVanilla.splits() {
+						// Stage EX is over
+						self.run.clear();
+					} else {
+						self.split();
+					}
 					self.split_delay = None
 				}
 			}
@@ -266,6 +572,12 @@ impl ZeroSplitter {
 	}
 }
 
+fn scheduled_backup(db: &Database) {
+	if let Err(err) = backup::auto_backup(db) {
+		error!("Error making scheduled backup: {err:?}");
+	}
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Gamemode {
 	GreenOrange,
@@ -298,6 +610,17 @@ pub struct EntryDialogData {
 	pub mode: Gamemode,
 }
 
+/// Name of a split as shown in the split list
+fn split_name(mode: Gamemode, split: usize, descriptive: bool) -> String {
+	match mode {
+		// translate split number to stage/loop for GO
+		Gamemode::GreenOrange => format!("{}-{}", (split >> 2) + 1, (split & 3) + 1),
+		Gamemode::WhiteVanilla if descriptive => vanilla_descriptive_split_names(split).to_owned(),
+		Gamemode::WhiteVanilla => vanilla_split_names(split).to_owned(),
+		Gamemode::BlackOnion => todo!(),
+	}
+}
+
 fn vanilla_split_names(split: usize) -> &'static str {
 	[
 		"1-1", "1-2", "1-3", "1-4", "Bonus 1", "2-1", "2-2", "2-3", "2-4", "2-5", "2-6", "Bonus 2", "3-1", "3-2",
@@ -376,7 +699,27 @@ struct OldRun {
 struct CategoryManager {
 	categories: Vec<Category>,
 	current: usize,
+	comparison: Comparison,
 	comparison_cache: Vec<i32>,
+	goals: Vec<Goal>,
+	stats: CategoryStats,
+	/// Loads and writes sent to the database that haven't come back yet, see [`Self::poll`]
+	pending: Vec<Pending<Loaded>>,
+	/// Why the last load or write failed
+	error: Option<String>,
+}
+
+/// Something the database worker finished for the [`CategoryManager`].
+/// Results for a category are tagged with its id, so they're dropped if another one was picked meanwhile.
+enum Loaded {
+	Categories(Vec<Category>),
+	Created(Category),
+	Goals(i64, Vec<Goal>),
+	Stats(i64, CategoryStats),
+	/// Splits of a comparison, along with the comparison they were calculated for
+	Comparison(i64, Comparison, Vec<i32>),
+	/// A change was written, there's nothing to update
+	Saved,
 }
 
 impl CategoryManager {
@@ -384,10 +727,24 @@ impl CategoryManager {
 		CategoryManager {
 			categories: Vec::new(),
 			current: 0,
+			comparison: Comparison::default(),
 			comparison_cache: Vec::new(),
+			goals: Vec::new(),
+			stats: CategoryStats::default(),
+			pending: Vec::new(),
+			error: None,
 		}
 	}
 
+	/// Read the categories from the database. Waits for the list, as nothing can be shown without it,
+	/// the rest of the current category follows in the background.
+	pub fn open(db: &DbWorker) -> Result<Self, ZeroError> {
+		let mut manager = Self::init();
+		manager.categories = db.call(|db| Ok(db.get_categories()?))?;
+		manager.refresh(db);
+		Ok(manager)
+	}
+
 	fn current(&self) -> &Category {
 		&self.categories.get(self.current).unwrap()
 	}
@@ -396,13 +753,110 @@ impl CategoryManager {
 		&mut self.categories[self.current]
 	}
 
-	#[must_use]
-	pub fn push(&mut self, name: String, mode: Gamemode, db: &Database) -> Result<(), ZeroError> {
-		let id = db.insert_new_category(name.clone(), mode)?;
-		self.categories.push(Category { name, mode, id });
+	/// Send a job to the database, its result is picked up by [`Self::poll`]
+	fn request(&mut self, db: &DbWorker, job: impl FnOnce(&Database) -> Result<Loaded, ZeroError> + Send + 'static) {
+		self.pending.push(db.request(job));
+	}
+
+	/// Whether anything is still being loaded or written
+	pub fn is_loading(&self) -> bool {
+		!self.pending.is_empty()
+	}
+
+	/// Pick up whatever the database has finished. Call this every frame.
+	pub fn poll(&mut self, db: &DbWorker) {
+		let mut loaded = Vec::new();
+		self.pending.retain(|pending| match pending.poll() {
+			None => true,
+			Some(Ok(result)) => {
+				loaded.push(result);
+				false
+			}
+			Some(Err(err)) => {
+				error!("Category update failed: {err:?}");
+				self.error = Some(format!("{err:?}"));
+				false
+			}
+		});
+		for result in loaded {
+			self.apply(result, db);
+		}
+	}
+
+	/// Block until everything queued is done
+	#[cfg(test)]
+	pub fn wait(&mut self, db: &DbWorker) -> Result<(), ZeroError> {
+		while !self.pending.is_empty() {
+			let result = self.pending.remove(0).wait()?;
+			self.apply(result, db);
+		}
 		Ok(())
 	}
 
+	fn apply(&mut self, loaded: Loaded, db: &DbWorker) {
+		let current_id = self.categories.get(self.current).map(|c| c.id);
+		match loaded {
+			Loaded::Categories(categories) => {
+				self.categories = categories;
+				self.current = self
+					.categories
+					.iter()
+					.position(|c| Some(c.id) == current_id)
+					.unwrap_or(0);
+				self.refresh(db);
+			}
+			Loaded::Created(category) => {
+				self.categories.push(category);
+				self.sort();
+			}
+			Loaded::Goals(id, goals) if Some(id) == current_id => {
+				self.goals = goals;
+				if let Comparison::Goal(id) = self.comparison
+					&& !self.goals.iter().any(|g| g.id == id)
+				{
+					self.set_comparison(Comparison::PersonalBest, db);
+				}
+			}
+			Loaded::Stats(id, stats) if Some(id) == current_id => self.stats = stats,
+			Loaded::Comparison(id, comparison, splits) if Some(id) == current_id && comparison == self.comparison => {
+				self.comparison_cache = splits
+			}
+			_ => {}
+		}
+	}
+
+	/// Add a category. It shows up in the list once it's written.
+	pub fn push(&mut self, name: String, mode: Gamemode, db: &DbWorker) {
+		self.request(db, move |db| {
+			let id = db.insert_new_category(name.clone(), mode)?;
+			Ok(Loaded::Created(Category {
+				name,
+				mode,
+				id,
+				meta: Default::default(),
+			}))
+		});
+	}
+
+	/// Save new metadata for the current category and put it in its new place in the list
+	pub fn set_metadata(&mut self, meta: CategoryMetadata, db: &DbWorker) -> Result<(), ZeroError> {
+		let id = self.current().id;
+		let new_meta = meta.clone();
+		db.call(move |db| Ok(db.update_category_metadata(id, &new_meta)?))?;
+		self.current_mut().meta = meta;
+		self.sort();
+		Ok(())
+	}
+
+	/// Order the categories like the database does, keeping the same category selected
+	fn sort(&mut self) {
+		let current_id = self.categories.get(self.current).map(|c| c.id);
+		self.categories.sort_by_key(|c| (c.meta.sort_order, c.id));
+		if let Some(idx) = self.categories.iter().position(|c| Some(c.id) == current_id) {
+			self.current = idx;
+		}
+	}
+
 	pub fn index(&self, index: usize) -> Option<&Category> {
 		self.categories.get(index)
 	}
@@ -411,56 +865,211 @@ impl CategoryManager {
 		self.categories.len()
 	}
 
-	/// Populate the CategoryManager with data from the database
-	pub fn load(&mut self, db: &Database) -> Result<(), ZeroError> {
-		self.categories = db.get_categories()?;
-		Ok(())
+	/// Read the categories from the database again, keeping the same one selected if it's still there
+	pub fn load(&mut self, db: &DbWorker) {
+		self.request(db, |db| Ok(Loaded::Categories(db.get_categories()?)));
+	}
+
+	/// Reload everything about the current category
+	fn refresh(&mut self, db: &DbWorker) {
+		self.error = None;
+		self.refresh_goals(db);
+		self.refresh_stats(db);
+		self.refresh_comparison(db);
 	}
 
-	pub fn delete_current(&mut self, db: &Database) -> Result<usize, ZeroError> {
+	pub fn delete_current(&mut self, db: &DbWorker) {
 		if self.categories.len() > 1 {
-			db.delete_category(self.categories.remove(self.current))
-				.map_err(ZeroError::DatabaseError)
+			let category = self.categories.remove(self.current);
+			self.request(db, move |db| {
+				db.delete_category(category)?;
+				Ok(Loaded::Saved)
+			});
+			self.current = self.current.min(self.categories.len() - 1);
+			self.clear();
+			self.refresh(db);
 		} else {
-			Err(ZeroError::Illegal)
+			self.error = Some(format!("{:?}", ZeroError::Illegal));
 		}
 	}
 
-	pub fn rename_current(&mut self, db: &Database, new_name: String) -> Result<usize, ZeroError> {
+	pub fn rename_current(&mut self, db: &DbWorker, new_name: String) {
+		let category = self.current().clone();
 		self.current_mut().name = new_name.clone();
-		db.rename_category(self.current(), new_name)
-			.map_err(ZeroError::DatabaseError)
+		self.request(db, move |db| {
+			db.rename_category(&category, new_name)?;
+			Ok(Loaded::Saved)
+		});
 	}
 
 	/// Sets the current selected category by index.
 	/// Returns true if the category changed
-	pub fn set_current(&mut self, new_idx: usize, db: &Database) -> Result<bool, ZeroError> {
+	pub fn set_current(&mut self, new_idx: usize, db: &DbWorker) -> Result<bool, ZeroError> {
 		if new_idx == self.current {
 			return Ok(false);
 		} else if new_idx >= self.categories.len() {
 			return Err(ZeroError::CategoryOutOfRange);
 		}
 		self.current = new_idx;
-		self.refresh_comparison(db)?;
+		self.clear();
+		self.refresh(db);
 
 		Ok(true)
 	}
 
-	pub fn get_comparison(&self) -> &Vec<i32> {
-		if self.comparison_cache.len() == 0 {
-			panic!()
-		}
+	/// Forget what was loaded for the previous category, so it isn't shown for this one
+	fn clear(&mut self) {
+		self.goals.clear();
+		self.stats = CategoryStats::default();
+		self.comparison_cache.clear();
+	}
+
+	/// Splits of the comparison, empty until it's loaded
+	pub fn get_comparison(&self) -> &[i32] {
 		&self.comparison_cache
 	}
 
-	pub fn refresh_comparison(&mut self, db: &Database) -> Result<(), ZeroError> {
-		self.comparison_cache = match db.get_pb_run(self) {
-			Ok((scores, _, mode)) if mode == self.current().mode => scores,
+	pub fn comparison(&self) -> Comparison {
+		self.comparison
+	}
+
+	pub fn comparison_name(&self) -> String {
+		match self.comparison {
+			Comparison::Goal(id) => self
+				.goals
+				.iter()
+				.find(|g| g.id == id)
+				.map_or_else(|| self.comparison.name(), |g| format!("Goal: {}", g.name)),
+			comparison => comparison.name(),
+		}
+	}
+
+	/// Goals saved for the current category
+	pub fn goals(&self) -> &[Goal] {
+		&self.goals
+	}
+
+	/// Reload the current category's goals. Once they're in, the comparison goes back to
+	/// the PB if the goal being compared against is gone.
+	pub fn refresh_goals(&mut self, db: &DbWorker) {
+		let category = self.current().clone();
+		self.request(db, move |db| Ok(Loaded::Goals(category.id, db.get_goals(&category)?)));
+	}
+
+	/// Change what the current run is compared against
+	pub fn set_comparison(&mut self, comparison: Comparison, db: &DbWorker) {
+		self.comparison = comparison;
+		self.comparison_cache.clear();
+		self.refresh_comparison(db);
+	}
+
+	/// PB, best splits and attempts of the current category, as of the last [`Self::refresh_stats`]
+	pub fn stats(&self) -> &CategoryStats {
+		&self.stats
+	}
+
+	/// Recalculate the current category's stats.
+	/// Call this whenever the category changes or a run is added to or removed from it.
+	pub fn refresh_stats(&mut self, db: &DbWorker) {
+		let category = self.current().clone();
+		self.request(db, move |db| {
+			Ok(Loaded::Stats(category.id, CategoryStats::load(db, &category)?))
+		});
+	}
+
+	/// Recalculate the comparison's splits. Runs after anything already sent to the database,
+	/// so a run that was just saved counts.
+	pub fn refresh_comparison(&mut self, db: &DbWorker) {
+		let category = self.current().clone();
+		let comparison = self.comparison;
+		self.request(db, move |db| {
+			let mode = category.mode;
+			let splits = match comparison {
+				Comparison::PersonalBest => match db.get_pb_run(&category) {
+					Ok((scores, ..)) => scores,
+					Err(rusqlite::Error::QueryReturnedNoRows) => vec![0; mode.splits()],
+					Err(e) => return Err(ZeroError::DatabaseError(e)),
+				},
+				Comparison::Goal(id) => match db.get_goals(&category)?.into_iter().find(|g| g.id == id) {
+					Some(goal) => goal.splits,
+					None => vec![0; mode.splits()],
+				},
+				comparison => comparison.calculate(&db.get_split_records(&category.name)?, mode),
+			};
+			Ok(Loaded::Comparison(category.id, comparison, splits))
+		});
+	}
+}
+
+/// Stats of a category that are shown every frame, so they're kept in memory instead of queried each time
+#[derive(Debug, Default, Clone, PartialEq)]
+struct CategoryStats {
+	/// Splits of the PB run, empty if there are no runs yet
+	pb_splits: Vec<i32>,
+	pb: i32,
+	pb_run: Option<i64>,
+	/// Highest multiplier in each split of the PB run, 0 where it wasn't recorded
+	pb_peak_mults: Vec<i64>,
+	/// Best score of each split in any run
+	gold_splits: Vec<i32>,
+	sum_of_best: i32,
+	/// Number of runs saved, imported runs included
+	attempts: usize,
+}
+
+impl CategoryStats {
+	fn load(db: &Database, category: &Category) -> Result<Self, ZeroError> {
+		let (pb_splits, pb, pb_run) = match db.get_pb_run(category) {
+			Ok((scores, total, pb_mode, run_id)) if pb_mode == category.mode => (scores, total, Some(run_id)),
 			Ok(_) => return Err(ZeroError::DifficultyMismatch),
-			Err(rusqlite::Error::QueryReturnedNoRows) => vec![0; self.current().mode.splits()],
+			Err(rusqlite::Error::QueryReturnedNoRows) => (Vec::new(), 0, None),
 			Err(e) => return Err(ZeroError::DatabaseError(e)),
 		};
-		Ok(())
+		let gold_splits = match db.get_gold_splits(category) {
+			Ok(golds) => golds,
+			Err(rusqlite::Error::QueryReturnedNoRows) => Vec::new(),
+			Err(e) => return Err(ZeroError::DatabaseError(e)),
+		};
+
+		let pb_peak_mults = match pb_run {
+			Some(run_id) => {
+				let mut mults = vec![0; category.mode.splits()];
+				for (split_num, mult) in db.get_peak_mults(run_id)? {
+					if let Some(slot) = mults.get_mut(split_num as usize) {
+						*slot = mult.unwrap_or(0);
+					}
+				}
+				mults
+			}
+			None => Vec::new(),
+		};
+
+		Ok(CategoryStats {
+			pb_splits,
+			pb,
+			pb_run,
+			pb_peak_mults,
+			sum_of_best: gold_splits.iter().sum(),
+			gold_splits,
+			attempts: db.count_runs(category)?,
+		})
+	}
+
+	/// Count a run that's still being saved, so the PB and golds are right for the next run straight away.
+	/// The stats loaded once it's written replace these.
+	fn add_run(&mut self, scores: &[i32]) {
+		let total = scores.iter().sum();
+		if total > self.pb {
+			self.pb = total;
+			self.pb_splits = scores.to_vec();
+		}
+		if self.gold_splits.len() < scores.len() {
+			self.gold_splits.resize(scores.len(), 0);
+		}
+		for (gold, &score) in self.gold_splits.iter_mut().zip(scores) {
+			*gold = (*gold).max(score);
+		}
+		self.sum_of_best = self.gold_splits.iter().sum();
 	}
 }
 
@@ -469,6 +1078,22 @@ struct Category {
 	name: String,
 	mode: Gamemode,
 	id: i64,
+	meta: CategoryMetadata,
+}
+
+/// Details of a category the user can edit, none of which change how runs are tracked
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+struct CategoryMetadata {
+	notes: String,
+	game_version: String,
+	/// e.g. "no continues"
+	ruleset: String,
+	/// 0xRRGGBB the category's name is shown in
+	color: Option<u32>,
+	/// Categories are listed by this, then by when they were made
+	sort_order: i32,
+	/// Hidden from the category dropdown
+	archived: bool,
 }
 
 #[derive(Debug)]
@@ -487,6 +1112,18 @@ enum ZeroError {
 	ReqwestError(reqwest::Error),
 	ParseError,
 	ConfigError(String),
+	SchemaTooNew(i32),
+	CsvError(csv::Error),
+	ImportError(String),
+	JSONError(serde_json::Error),
+	ArchiveTooNew(String),
+	ArgumentError(String),
+	ProfileError(String),
+	/// The database worker thread stopped
+	DatabaseClosed,
+	/// The thread receiving frames from the game stopped
+	GameListenerClosed,
+	ServerError(String),
 }
 
 impl From<reqwest::Error> for ZeroError {
@@ -495,6 +1132,18 @@ impl From<reqwest::Error> for ZeroError {
 	}
 }
 
+impl From<csv::Error> for ZeroError {
+	fn from(value: csv::Error) -> Self {
+		ZeroError::CsvError(value)
+	}
+}
+
+impl From<serde_json::Error> for ZeroError {
+	fn from(value: serde_json::Error) -> Self {
+		ZeroError::JSONError(value)
+	}
+}
+
 impl From<rusqlite::Error> for ZeroError {
 	fn from(value: rusqlite::Error) -> Self {
 		ZeroError::DatabaseError(value)
@@ -0,0 +1,164 @@
+use std::{
+	env, fs,
+	path::{Path, PathBuf},
+	process::Command,
+	sync::OnceLock,
+};
+
+use crate::{ZeroError, cli::flag};
+
+/// Name of the profile that lives directly in the data directory, where older versions kept everything
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Folder in the data directory holding every profile other than the default
+const PROFILES_DIR: &str = "profiles";
+
+/// File in the data directory remembering the profile that was used last
+const LAST_PROFILE_FILE: &str = "last_profile";
+
+/// Environment variable that can set the data directory instead of `--data-dir`
+const DATA_DIR_VAR: &str = "ZEROSPLITTER_DATA_DIR";
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+/// Where the database, config and backups are kept. Each profile has its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+	pub name: String,
+	/// Folder holding every profile
+	pub data_dir: PathBuf,
+}
+
+impl Profile {
+	/// Folder holding this profile's files
+	pub fn dir(&self) -> PathBuf {
+		if self.name == DEFAULT_PROFILE {
+			self.data_dir.clone()
+		} else {
+			self.data_dir.join(PROFILES_DIR).join(&self.name)
+		}
+	}
+}
+
+/// Pick the profile for this launch from `--data-dir <dir>` and `--profile <name>`.
+/// Without them, the data directory is `ZEROSPLITTER_DATA_DIR` or the working directory,
+/// and the profile is the one used last.
+pub fn init(args: &[String]) -> Result<(), ZeroError> {
+	let data_dir = match flag(args, "--data-dir")? {
+		Some(dir) => PathBuf::from(dir),
+		None => env::var_os(DATA_DIR_VAR).map_or_else(|| PathBuf::from("."), PathBuf::from),
+	};
+	let name = match flag(args, "--profile")? {
+		Some(name) => name,
+		None => fs::read_to_string(data_dir.join(LAST_PROFILE_FILE))
+			.map(|s| s.trim().to_owned())
+			.ok()
+			.filter(|name| check_name(name).is_ok())
+			.unwrap_or_else(|| DEFAULT_PROFILE.to_owned()),
+	};
+	check_name(&name)?;
+
+	let profile = Profile { name, data_dir };
+	fs::create_dir_all(profile.dir())?;
+
+	PROFILE.set(profile).map_err(|_| ZeroError::StaticAlreadyInit)
+}
+
+/// Make the current profile the one picked next time without `--profile`.
+/// Only the window does this, so commands run from scripts don't change it.
+pub fn remember_current() -> Result<(), ZeroError> {
+	let profile = current();
+	Ok(fs::write(profile.data_dir.join(LAST_PROFILE_FILE), &profile.name)?)
+}
+
+/// The profile in use. The default profile in the working directory if [`init`] wasn't called.
+pub fn current() -> &'static Profile {
+	PROFILE.get_or_init(|| Profile {
+		name: DEFAULT_PROFILE.to_owned(),
+		data_dir: PathBuf::from("."),
+	})
+}
+
+/// Path of a file or folder in the current profile
+pub fn path(file: impl AsRef<Path>) -> PathBuf {
+	current().dir().join(file)
+}
+
+/// Every profile in the data directory, the default first
+pub fn list_profiles() -> Result<Vec<String>, ZeroError> {
+	let mut profiles = match fs::read_dir(current().data_dir.join(PROFILES_DIR)) {
+		Ok(entries) => entries
+			.filter_map(|entry| entry.ok())
+			.filter(|entry| entry.path().is_dir())
+			.map(|entry| entry.file_name().to_string_lossy().into_owned())
+			.filter(|name| check_name(name).is_ok())
+			.collect(),
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+		Err(err) => return Err(err.into()),
+	};
+	profiles.sort();
+	profiles.insert(0, DEFAULT_PROFILE.to_owned());
+	Ok(profiles)
+}
+
+/// Make a new, empty profile. Its database and config are created when it's first used.
+pub fn create_profile(name: &str) -> Result<(), ZeroError> {
+	check_name(name)?;
+	if list_profiles()?.iter().any(|p| p == name) {
+		return Err(ZeroError::ProfileError(format!("Profile {name} already exists")));
+	}
+	let profile = Profile {
+		name: name.to_owned(),
+		data_dir: current().data_dir.clone(),
+	};
+	Ok(fs::create_dir_all(profile.dir())?)
+}
+
+/// Start another ZeroSplitter with the given profile. The caller should close this one.
+pub fn relaunch_with(name: &str) -> Result<(), ZeroError> {
+	check_name(name)?;
+	Command::new(env::current_exe()?)
+		.arg("--data-dir")
+		.arg(&current().data_dir)
+		.arg("--profile")
+		.arg(name)
+		.spawn()?;
+	Ok(())
+}
+
+/// Profile names become folder names, so keep them to letters, digits, spaces, `-` and `_`
+fn check_name(name: &str) -> Result<(), ZeroError> {
+	if name.trim().is_empty() || name.trim() != name {
+		return Err(ZeroError::ProfileError(
+			"Profile names can't be empty or start or end with a space".to_owned(),
+		));
+	}
+	if let Some(c) = name
+		.chars()
+		.find(|&c| !(c.is_alphanumeric() || c == ' ' || c == '-' || c == '_'))
+	{
+		return Err(ZeroError::ProfileError(format!("Profile names can't contain '{c}'")));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn profile_dirs() {
+		let data_dir = PathBuf::from("data");
+		let profile = |name: &str| Profile {
+			name: name.to_owned(),
+			data_dir: data_dir.clone(),
+		};
+		assert_eq!(profile(DEFAULT_PROFILE).dir(), data_dir);
+		assert_eq!(profile("practice").dir(), data_dir.join("profiles").join("practice"));
+
+		assert!(check_name("practice/old").is_err());
+		assert!(check_name("../up").is_err());
+		assert!(check_name(" ").is_err());
+		assert!(check_name("co-op_2").is_ok());
+	}
+}
@@ -0,0 +1,162 @@
+use crate::{Gamemode, database::SplitRecord};
+
+/// Pattern and dynamic rank at the end of a split
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Ranks {
+	pub pattern: f64,
+	pub dynamic: f64,
+}
+
+/// Which of the two ranks to look at
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RankKind {
+	#[default]
+	Pattern,
+	Dynamic,
+}
+
+impl Ranks {
+	pub fn get(&self, kind: RankKind) -> f64 {
+		match kind {
+			RankKind::Pattern => self.pattern,
+			RankKind::Dynamic => self.dynamic,
+		}
+	}
+}
+
+/// What a category's history says about rank at one split
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SplitRanks {
+	pub pb: Option<Ranks>,
+	pub average: Option<Ranks>,
+	/// How rank at the end of the split goes with the score of the split, from -1 to 1.
+	/// `None` with fewer than 3 runs or when the rank or score never changes.
+	pub pattern_correlation: Option<f64>,
+	pub dynamic_correlation: Option<f64>,
+	/// Number of runs with ranks recorded for this split
+	pub runs: usize,
+}
+
+impl SplitRanks {
+	pub fn correlation(&self, kind: RankKind) -> Option<f64> {
+		match kind {
+			RankKind::Pattern => self.pattern_correlation,
+			RankKind::Dynamic => self.dynamic_correlation,
+		}
+	}
+}
+
+/// Rank stats for each split. Runs without ranks (imported, or from before ranks were saved) are left out,
+/// and so is the split a run was reset or died on.
+pub fn rank_history(records: &[SplitRecord], pb_run: Option<i64>, mode: Gamemode) -> Vec<SplitRanks> {
+	(0..mode.splits())
+		.map(|split| {
+			let samples = records
+				.iter()
+				.filter(|r| r.split_num as usize == split && r.is_whole(mode))
+				.filter_map(|r| {
+					let ranks = Ranks {
+						pattern: r.pattern_rank?,
+						dynamic: r.dynamic_rank?,
+					};
+					Some((r.run_id, r.score as f64, ranks))
+				})
+				.collect::<Vec<_>>();
+			if samples.is_empty() {
+				return SplitRanks::default();
+			}
+
+			let runs = samples.len();
+			let average = Ranks {
+				pattern: samples.iter().map(|s| s.2.pattern).sum::<f64>() / runs as f64,
+				dynamic: samples.iter().map(|s| s.2.dynamic).sum::<f64>() / runs as f64,
+			};
+			let scores = samples.iter().map(|s| s.1).collect::<Vec<f64>>();
+			let ranks = |kind| samples.iter().map(|s| s.2.get(kind)).collect::<Vec<f64>>();
+
+			SplitRanks {
+				pb: samples.iter().find(|s| Some(s.0) == pb_run).map(|s| s.2),
+				average: Some(average),
+				pattern_correlation: correlation(&ranks(RankKind::Pattern), &scores),
+				dynamic_correlation: correlation(&ranks(RankKind::Dynamic), &scores),
+				runs,
+			}
+		})
+		.collect()
+}
+
+/// Pearson correlation coefficient
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+	if xs.len() < 3 {
+		return None;
+	}
+	let n = xs.len() as f64;
+	let mean_x = xs.iter().sum::<f64>() / n;
+	let mean_y = ys.iter().sum::<f64>() / n;
+
+	let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+	for (x, y) in xs.iter().zip(ys) {
+		cov += (x - mean_x) * (y - mean_y);
+		var_x += (x - mean_x).powi(2);
+		var_y += (y - mean_y).powi(2);
+	}
+	if var_x == 0.0 || var_y == 0.0 {
+		return None;
+	}
+	Some(cov / (var_x * var_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ranks_from_history() {
+		let records = [
+			SplitRecord {
+				pattern_rank: Some(1.0),
+				dynamic_rank: Some(3.0),
+				..SplitRecord::new(1, 0, 100)
+			},
+			SplitRecord {
+				pattern_rank: Some(2.0),
+				dynamic_rank: Some(2.0),
+				..SplitRecord::new(2, 0, 200)
+			},
+			SplitRecord {
+				pattern_rank: Some(3.0),
+				dynamic_rank: Some(1.0),
+				..SplitRecord::new(3, 0, 300)
+			},
+			// imported, no ranks
+			SplitRecord::new(4, 0, 1000),
+			// reset partway through the split
+			SplitRecord {
+				pattern_rank: Some(9.0),
+				dynamic_rank: Some(9.0),
+				final_split: Some(true),
+				..SplitRecord::new(5, 0, 10)
+			},
+		];
+		let history = rank_history(&records, Some(3), Gamemode::GreenOrange);
+
+		assert_eq!(history[0].runs, 3);
+		assert_eq!(
+			history[0].pb,
+			Some(Ranks {
+				pattern: 3.0,
+				dynamic: 1.0
+			})
+		);
+		assert_eq!(
+			history[0].average,
+			Some(Ranks {
+				pattern: 2.0,
+				dynamic: 2.0
+			})
+		);
+		assert!((history[0].pattern_correlation.unwrap() - 1.0).abs() < 1e-9);
+		assert!((history[0].dynamic_correlation.unwrap() + 1.0).abs() < 1e-9);
+		assert_eq!(history[1], SplitRanks::default());
+	}
+}
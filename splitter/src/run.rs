@@ -2,7 +2,7 @@ use common::FrameData;
 
 use crate::{Gamemode, ZeroError};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Run {
 	Inactive,
 	Active {
@@ -11,6 +11,8 @@ pub enum Run {
 		score: i32,
 		current_split: usize,
 		split_base_score: i32,
+		/// The game was played to the end, see [`Run::clear`]
+		cleared: bool,
 	},
 }
 
@@ -44,6 +46,7 @@ impl Run {
 			score: 0,
 			current_split: 0,
 			split_base_score: 0,
+			cleared: false,
 		};
 	}
 
@@ -60,6 +63,7 @@ impl Run {
 				score: 0,
 				current_split: 0,
 				split_base_score: 0,
+				cleared: false,
 			},
 		}
 	}
@@ -71,6 +75,7 @@ impl Run {
 			score,
 			current_split,
 			split_base_score,
+			..
 		} = self
 		{
 			if *difficulty == Gamemode::from(frame.difficulty) {
@@ -78,13 +83,16 @@ impl Run {
 				if *split_base_score > *score {
 					*split_base_score = 0
 				}
-				let data = SplitData::new(
-					frame.total_score() - *split_base_score,
-					frame.multiplier_one,
-					frame.pattern_rank,
-					frame.dynamic_rank,
-				);
-				*splits.get_mut(*current_split).unwrap() = data;
+				let split = splits.get_mut(*current_split).unwrap();
+				*split = SplitData {
+					score: frame.total_score() - *split_base_score,
+					mult: frame.multiplier_one,
+					mult_p2: frame.multiplier_two,
+					peak_mult: split.peak_mult.max(frame.multiplier_one),
+					peak_mult_p2: split.peak_mult_p2.max(frame.multiplier_two),
+					pattern_rank: frame.pattern_rank,
+					dynamic_rank: frame.dynamic_rank,
+				};
 
 				Ok(())
 			} else {
@@ -140,6 +148,17 @@ impl Run {
 		}
 	}
 
+	/// Mark the run as finished, once the game shows it was played to the end
+	pub fn clear(&mut self) {
+		if let Run::Active { cleared, .. } = self {
+			*cleared = true;
+		}
+	}
+
+	pub fn is_cleared(&self) -> bool {
+		matches!(self, Run::Active { cleared: true, .. })
+	}
+
 	pub fn is_active(&self) -> bool {
 		match self {
 			Run::Inactive => false,
@@ -151,27 +170,24 @@ impl Run {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SplitData {
 	pub score: i32,
+	/// Multiplier on the latest frame of the split, the end of the split once it's over
 	pub mult: u32,
+	pub mult_p2: u32,
+	/// Highest multiplier reached during the split
+	pub peak_mult: u32,
+	pub peak_mult_p2: u32,
 	pub pattern_rank: f32,
 	pub dynamic_rank: f32,
 }
 
-impl SplitData {
-	fn new(score: i32, mult: u32, pattern_rank: f32, dynamic_rank: f32) -> Self {
-		Self {
-			score,
-			mult,
-			pattern_rank,
-			dynamic_rank,
-		}
-	}
-}
-
 impl Default for SplitData {
 	fn default() -> Self {
 		Self {
 			score: Default::default(),
 			mult: Default::default(),
+			mult_p2: Default::default(),
+			peak_mult: Default::default(),
+			peak_mult_p2: Default::default(),
 			pattern_rank: Default::default(),
 			dynamic_rank: Default::default(),
 		}
@@ -0,0 +1,458 @@
+use std::sync::{Arc, Mutex};
+
+use eframe::egui::{
+	Context, DragValue, Grid, Id, RichText, ScrollArea, Separator, TextEdit, Ui, ViewportBuilder, ViewportId,
+};
+
+use crate::{
+	Category, CategoryManager, DB_POLL_INTERVAL, ZeroError,
+	comparison::{Comparison, GoalBasis, distribute_goal},
+	history::{revision, tag_filter},
+	ranks::{RankKind, rank_history},
+	run::Run,
+	split_name,
+	theme::{DARK_ORANGE, GREEN, LIGHT_ORANGE},
+	worker::{DbWorker, Pending},
+};
+
+/// Window with statistics about the current category
+pub fn stats_window(ctx: &Context, db: &DbWorker, categories: &mut CategoryManager, run: &Run, open: &mut bool) {
+	ctx.show_viewport_immediate(
+		ViewportId::from_hash_of("stats_viewport"),
+		ViewportBuilder::default().with_title("Statistics"),
+		|ctx, _| {
+			eframe::egui::CentralPanel::default().show(ctx, |ui| {
+				ScrollArea::vertical().show(ui, |ui| {
+					let category = categories.current();
+					ui.horizontal(|ui| {
+						ui.label(RichText::new(&category.name).color(GREEN).heading());
+						ui.label(format!("{} attempts", categories.stats().attempts));
+					});
+
+					// DATE RANGE
+					let (from, to) = date_range(ui);
+					let tag = ui
+						.horizontal(|ui| tag_filter(ui, db, categories, Id::new("stats_tag")))
+						.inner;
+
+					// PASS RATE
+					ui.horizontal(|ui| {
+						ui.label(RichText::new("Pass rate").color(GREEN).heading());
+						ui.add(Separator::default().horizontal())
+					});
+					pass_rate_table(ui, db, categories, from, to, tag);
+
+					// GOALS
+					ui.horizontal(|ui| {
+						ui.label(RichText::new("Goals").color(GREEN).heading());
+						ui.add(Separator::default().horizontal())
+					});
+					goal_section(ui, db, categories);
+
+					// RANK
+					ui.horizontal(|ui| {
+						ui.label(RichText::new("Rank").color(GREEN).heading());
+						ui.add(Separator::default().horizontal())
+					});
+					rank_table(ui, db, categories, run);
+
+					// MULTIPLIER
+					ui.horizontal(|ui| {
+						ui.label(RichText::new("Multiplier").color(GREEN).heading());
+						ui.add(Separator::default().horizontal())
+					});
+					mult_table(ui, categories, run);
+				});
+			});
+
+			if ctx.input(|i| i.viewport().close_requested()) {
+				*open = false
+			};
+		},
+	);
+}
+
+/// Optional `YYYY-MM-DD` dates limiting which runs are counted. Invalid dates are ignored.
+fn date_range(ui: &mut Ui) -> (Option<String>, Option<String>) {
+	let ctx = ui.ctx().clone();
+	let mut dates = [Id::new("stats_from"), Id::new("stats_to")]
+		.map(|id| (id, ctx.data(|data| data.get_temp::<String>(id)).unwrap_or_default()));
+
+	ui.horizontal(|ui| {
+		ui.label("From");
+		ui.add(
+			TextEdit::singleline(&mut dates[0].1)
+				.hint_text("YYYY-MM-DD")
+				.desired_width(80.0),
+		);
+		ui.label("to");
+		ui.add(
+			TextEdit::singleline(&mut dates[1].1)
+				.hint_text("YYYY-MM-DD")
+				.desired_width(80.0),
+		);
+	});
+
+	let [from, to] = dates.map(|(id, date)| {
+		let valid = is_date(&date);
+		if !valid && !date.is_empty() {
+			ui.colored_label(DARK_ORANGE, format!("\"{date}\" isn't a YYYY-MM-DD date"));
+		}
+		ctx.data_mut(|data| data.insert_temp(id, date.clone()));
+		valid.then_some(date)
+	});
+	(from, to)
+}
+
+fn is_date(date: &str) -> bool {
+	let parts = date.split('-').collect::<Vec<_>>();
+	parts.len() == 3
+		&& [4, 2, 2]
+			.iter()
+			.zip(&parts)
+			.all(|(&len, part)| part.len() == len && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Per split: how many runs got there, how many got past, and where runs end
+fn pass_rate_table(
+	ui: &mut Ui,
+	db: &DbWorker,
+	categories: &CategoryManager,
+	from: Option<String>,
+	to: Option<String>,
+	tag: Option<String>,
+) {
+	let category = categories.current().clone();
+	let key = (
+		category.id,
+		categories.stats().attempts,
+		revision(ui.ctx()),
+		from.clone(),
+		to.clone(),
+		tag.clone(),
+	);
+	let rates = match cached(ui, Id::new("pass_rates"), key, || {
+		let category = category.clone();
+		db.request(move |db| Ok(db.get_pass_rates(&category, from.as_deref(), to.as_deref(), tag.as_deref())?))
+	}) {
+		Some(Ok(rates)) => rates,
+		Some(Err(err)) => {
+			ui.colored_label(DARK_ORANGE, format!("Couldn't read pass rates: {err}"));
+			return;
+		}
+		None => {
+			ui.label("Loading...");
+			return;
+		}
+	};
+	if rates.is_empty() {
+		ui.label("No recorded runs yet");
+		return;
+	}
+
+	let most_ended = rates.iter().map(|r| r.ended).max().unwrap_or(0);
+	Grid::new("pass_rate").striped(true).show(ui, |ui| {
+		for header in ["Split", "Reached", "Passed", "Pass %", "Ended"] {
+			ui.colored_label(GREEN, header);
+		}
+		ui.end_row();
+
+		for rate in &rates {
+			ui.label(split_name(category.mode, rate.split_num, true));
+			ui.label(rate.reached.to_string());
+			ui.label(rate.passed.to_string());
+			ui.label(format!("{:.1}%", rate.percentage()));
+			if rate.ended == most_ended && most_ended > 0 {
+				ui.colored_label(LIGHT_ORANGE, rate.ended.to_string())
+					.on_hover_text("Runs end here the most");
+			} else {
+				ui.label(rate.ended.to_string());
+			}
+			ui.end_row();
+		}
+	});
+}
+
+/// Rank at the end of each split of the current run next to the PB's and the average,
+/// and how rank has gone with the split's score over every run
+fn rank_table(ui: &mut Ui, db: &DbWorker, categories: &CategoryManager, run: &Run) {
+	let ctx = ui.ctx().clone();
+	let kind_id = Id::new("rank_kind");
+	let mut kind = ctx.data(|data| data.get_temp::<RankKind>(kind_id)).unwrap_or_default();
+	ui.horizontal(|ui| {
+		ui.selectable_value(&mut kind, RankKind::Pattern, "Pattern rank");
+		ui.selectable_value(&mut kind, RankKind::Dynamic, "Dynamic rank");
+	});
+	ctx.data_mut(|data| data.insert_temp(kind_id, kind));
+
+	let category = categories.current().clone();
+	let mode = category.mode;
+	let pb_run = categories.stats().pb_run;
+	let key = (category.id, categories.stats().attempts);
+	let history = match cached(ui, Id::new("rank_history"), key, || {
+		db.request(move |db| Ok(rank_history(&db.get_split_records(&category.name)?, pb_run, mode)))
+	}) {
+		Some(Ok(history)) => history,
+		Some(Err(err)) => {
+			ui.colored_label(DARK_ORANGE, format!("Couldn't read ranks: {err}"));
+			return;
+		}
+		None => {
+			ui.label("Loading...");
+			return;
+		}
+	};
+
+	// Ranks are only known up to the split being played
+	let run_ranks = match (run.splits(), run.current_split()) {
+		(Ok(splits), Ok(current)) => splits
+			.iter()
+			.take(current + 1)
+			.map(|split| match kind {
+				RankKind::Pattern => split.pattern_rank as f64,
+				RankKind::Dynamic => split.dynamic_rank as f64,
+			})
+			.collect(),
+		_ => Vec::new(),
+	};
+
+	Grid::new("ranks").striped(true).show(ui, |ui| {
+		for header in ["Split", "Run", "vs PB", "vs Avg", "PB", "Avg"] {
+			ui.colored_label(GREEN, header);
+		}
+		ui.colored_label(GREEN, "Score corr.").on_hover_text(
+			"How rank at the end of the split goes with the split's score over all runs, from -1 to +1.\n\
+			Positive: the split scores more when you end it at a higher rank.",
+		);
+		ui.end_row();
+
+		for (n, split) in history.iter().enumerate() {
+			let run_rank = run_ranks.get(n).copied();
+			let pb = split.pb.map(|r| r.get(kind));
+			let average = split.average.map(|r| r.get(kind));
+
+			ui.label(split_name(mode, n, true));
+			ui.label(run_rank.map_or("--".to_owned(), |r| format!("{r:.2}")));
+			for compare in [pb, average] {
+				ui.label(match (run_rank, compare) {
+					(Some(r), Some(c)) => format!("{:+.2}", r - c),
+					_ => "--".to_owned(),
+				});
+			}
+			for compare in [pb, average] {
+				ui.label(compare.map_or("--".to_owned(), |r| format!("{r:.2}")));
+			}
+			match split.correlation(kind) {
+				Some(c) => ui
+					.label(format!("{c:+.2}"))
+					.on_hover_text(format!("Over {} runs", split.runs)),
+				None => ui.label("--"),
+			};
+			ui.end_row();
+		}
+	});
+}
+
+/// Peak multiplier of each split of the current run against the PB's, and where the chain was dropped
+fn mult_table(ui: &mut Ui, categories: &CategoryManager, run: &Run) {
+	let (Ok(splits), Ok(current)) = (run.splits(), run.current_split()) else {
+		ui.label("Waiting for a run to start...");
+		return;
+	};
+	let mode = categories.current().mode;
+	let pb_mults = &categories.stats().pb_peak_mults;
+
+	Grid::new("mults").striped(true).show(ui, |ui| {
+		for header in ["Split", "Peak", "End", "PB peak", "vs PB"] {
+			ui.colored_label(GREEN, header);
+		}
+		ui.end_row();
+
+		for (n, split) in splits.iter().enumerate().take(current + 1) {
+			let pb_mult = pb_mults.get(n).copied().unwrap_or(0);
+			ui.label(split_name(mode, n, true));
+			ui.label(format!("x{}", split.peak_mult));
+			if split.mult < split.peak_mult {
+				ui.colored_label(LIGHT_ORANGE, format!("x{}", split.mult))
+					.on_hover_text("The chain dropped during this split");
+			} else {
+				ui.label(format!("x{}", split.mult));
+			}
+			if pb_mult > 0 {
+				ui.label(format!("x{pb_mult}"));
+				ui.label(format!("{:+}", split.peak_mult as i64 - pb_mult));
+			} else {
+				ui.label("--");
+				ui.label("--");
+			}
+			ui.end_row();
+		}
+	});
+}
+
+/// Make a goal comparison by splitting a target score up over the splits, and list the saved goals
+fn goal_section(ui: &mut Ui, db: &DbWorker, categories: &mut CategoryManager) {
+	let ctx = ui.ctx().clone();
+	let name_id = Id::new("goal_name");
+	let target_id = Id::new("goal_target");
+	let basis_id = Id::new("goal_basis");
+	let status_id = Id::new("goal_status");
+
+	let mut name = ctx.data(|data| data.get_temp::<String>(name_id)).unwrap_or_default();
+	let mut target = ctx.data(|data| data.get_temp::<i32>(target_id)).unwrap_or_default();
+	let mut basis = ctx
+		.data(|data| data.get_temp::<GoalBasis>(basis_id))
+		.unwrap_or_default();
+
+	ui.horizontal(|ui| {
+		ui.label("Name");
+		ui.add(TextEdit::singleline(&mut name).desired_width(100.0));
+		ui.label("Target");
+		ui.add(DragValue::new(&mut target).range(0..=i32::MAX).speed(1000));
+	});
+	ui.horizontal(|ui| {
+		ui.label("Split up like");
+		ui.selectable_value(&mut basis, GoalBasis::GoldSplits, "Best splits");
+		ui.selectable_value(&mut basis, GoalBasis::Average, "Average");
+	});
+
+	let category = categories.current().clone();
+	let basis_splits = match basis {
+		GoalBasis::GoldSplits => Some(Ok(categories.stats().gold_splits.clone())),
+		GoalBasis::Average => {
+			let key = (category.id, categories.stats().attempts);
+			cached(ui, Id::new("goal_average_basis"), key, || {
+				let category = category.clone();
+				db.request(move |db| {
+					Ok(Comparison::Average.calculate(&db.get_split_records(&category.name)?, category.mode))
+				})
+			})
+		}
+	};
+	match basis_splits {
+		Some(Ok(mut basis_splits)) if target > 0 => {
+			basis_splits.resize(category.mode.splits(), 0);
+			let splits = distribute_goal(target, &basis_splits);
+			goal_preview(ui, &category, &splits);
+
+			if ui.button("SAVE GOAL").clicked() {
+				let taken = format!("A goal named {} already exists", name.trim());
+				let status = if name.trim().is_empty() {
+					"Give the goal a name first".to_owned()
+				} else if categories.goals().iter().any(|goal| goal.name == name.trim()) {
+					taken
+				} else {
+					let (goal_category, goal_name, goal_splits) =
+						(category.clone(), name.trim().to_owned(), splits.clone());
+					match db
+						.call(move |db| Ok(db.insert_goal(&goal_category, &goal_name, target, &goal_splits)?))
+						.inspect(|_| categories.refresh_goals(db))
+					{
+						Ok(_) => {
+							let saved = format!("Saved goal {}, pick it in the Compare dropdown", name.trim());
+							name.clear();
+							saved
+						}
+						// the goals of the category may not have been loaded yet
+						Err(ZeroError::DatabaseError(rusqlite::Error::SqliteFailure(err, _)))
+							if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+						{
+							taken
+						}
+						Err(err) => format!("Couldn't save goal: {err:?}"),
+					}
+				};
+				ctx.data_mut(|data| data.insert_temp(status_id, status));
+			}
+		}
+		Some(Ok(_)) => (),
+		Some(Err(err)) => {
+			ui.colored_label(DARK_ORANGE, format!("Couldn't read splits: {err}"));
+		}
+		None => {
+			ui.label("Loading...");
+		}
+	}
+	ui.label(ctx.data(|data| data.get_temp::<String>(status_id)).unwrap_or_default());
+
+	// Saved goals
+	let mut delete = None;
+	Grid::new("goals").striped(true).show(ui, |ui| {
+		for goal in categories.goals() {
+			ui.label(&goal.name);
+			ui.label(goal.target.to_string());
+			if ui.small_button("Delete").clicked() {
+				delete = Some(goal.id);
+			}
+			ui.end_row();
+		}
+	});
+	if let Some(goal_id) = delete
+		&& let Err(err) = db
+			.call(move |db| Ok(db.delete_goal(goal_id)?))
+			.inspect(|_| categories.refresh_goals(db))
+	{
+		ctx.data_mut(|data| data.insert_temp(status_id, format!("Couldn't delete goal: {err:?}")));
+	}
+
+	ctx.data_mut(|data| {
+		data.insert_temp(name_id, name);
+		data.insert_temp(target_id, target);
+		data.insert_temp(basis_id, basis);
+	});
+}
+
+fn goal_preview(ui: &mut Ui, category: &Category, splits: &[i32]) {
+	Grid::new("goal_preview").striped(true).show(ui, |ui| {
+		for header in ["Split", "Goal", "Total"] {
+			ui.colored_label(GREEN, header);
+		}
+		ui.end_row();
+
+		let mut total = 0;
+		for (n, split) in splits.iter().enumerate() {
+			total += split;
+			ui.label(split_name(category.mode, n, true));
+			ui.label(split.to_string());
+			ui.label(total.to_string());
+			ui.end_row();
+		}
+	});
+}
+
+/// A query sent by [`cached`], or its result once the worker has answered
+#[derive(Clone)]
+enum Query<T> {
+	Sent(Arc<Mutex<Pending<T>>>),
+	Done(Result<T, String>),
+}
+
+/// Send `query` to the database worker only when `key` changes instead of every frame, keeping the result in
+/// egui's temp data. `None` until the worker has answered.
+pub fn cached<K, T>(ui: &Ui, id: Id, key: K, query: impl FnOnce() -> Pending<T>) -> Option<Result<T, String>>
+where
+	K: PartialEq + Clone + Send + Sync + 'static,
+	T: Clone + Send + Sync + 'static,
+{
+	let ctx = ui.ctx();
+	let mut state = match ctx.data(|data| data.get_temp::<(K, Query<T>)>(id)) {
+		Some((cached_key, state)) if cached_key == key => state,
+		_ => Query::Sent(Arc::new(Mutex::new(query()))),
+	};
+	let polled = match &state {
+		Query::Sent(pending) => pending.lock().unwrap().poll(),
+		Query::Done(_) => None,
+	};
+	if let Some(result) = polled {
+		state = Query::Done(result.map_err(|err| format!("{err:?}")));
+	}
+	ctx.data_mut(|data| data.insert_temp(id, (key, state.clone())));
+	match state {
+		Query::Sent(_) => {
+			// The windows are drawn by the main viewport, so that's the one to wake up
+			ctx.request_repaint_after_for(DB_POLL_INTERVAL, ViewportId::ROOT);
+			None
+		}
+		Query::Done(result) => Some(result),
+	}
+}
@@ -2,23 +2,86 @@ use std::{
 	ffi::{OsString, c_void},
 	os::windows::ffi::OsStringExt,
 	path::PathBuf,
-	ptr, u32,
+	ptr,
+	sync::{
+		Condvar, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::Duration,
+	u32,
 };
 
+use log::error;
 use windows::{
 	Win32::{
 		Foundation::{CloseHandle, HANDLE, HMODULE},
 		Security::SECURITY_ATTRIBUTES,
 		System::{
+			Console::{ATTACH_PARENT_PROCESS, AttachConsole, SetConsoleCtrlHandler},
 			Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory},
 			Memory::{PAGE_PROTECTION_FLAGS, VIRTUAL_ALLOCATION_TYPE, VirtualAllocEx},
 			ProcessStatus::{EnumProcessModules, EnumProcesses, GetModuleFileNameExW, GetProcessImageFileNameW},
 			Threading::{CreateRemoteThread, LPTHREAD_START_ROUTINE, OpenProcess, PROCESS_ACCESS_RIGHTS},
 		},
+		UI::WindowsAndMessaging::{MB_ICONERROR, MB_OK, MessageBoxW},
 	},
-	core::Error,
+	core::{BOOL, Error, HSTRING},
 };
 
+/// Windows ends the program a few seconds after the console is closed, whether or not it's done
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Set once the console asked the program to stop
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Set once the program is done cleaning up, see [`stopped`]
+static STOPPED: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+/// Print to the terminal the program was started from. Release builds are GUI programs,
+/// which Windows doesn't give a console of their own.
+pub fn attach_console() {
+	// Fails when not started from a terminal, and then there's nowhere to print anyway
+	let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+/// Show an error in a message box and wait for it to be closed, for when there's no window to show it in
+pub fn show_error(title: &str, message: &str) {
+	unsafe {
+		MessageBoxW(
+			None,
+			&HSTRING::from(message),
+			&HSTRING::from(title),
+			MB_OK | MB_ICONERROR,
+		);
+	}
+}
+
+/// Catch Ctrl+C, Ctrl+Break and the console window being closed instead of ending the program right away.
+/// Check [`stop_requested`] and call [`stopped`] when done cleaning up.
+pub fn catch_console_stop() -> Result<(), Error> {
+	unsafe { SetConsoleCtrlHandler(Some(console_handler), true) }
+}
+
+pub fn stop_requested() -> bool {
+	STOP_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Let the console go ahead with stopping the program
+pub fn stopped() {
+	let (done, signal) = &STOPPED;
+	*done.lock().unwrap() = true;
+	signal.notify_all();
+}
+
+unsafe extern "system" fn console_handler(_ctrl_type: u32) -> BOOL {
+	STOP_REQUESTED.store(true, Ordering::SeqCst);
+	// Windows ends the program once this returns, so hold on until it's cleaned up
+	let (done, signal) = &STOPPED;
+	let done = done.lock().unwrap();
+	let _ = signal.wait_timeout_while(done, STOP_TIMEOUT, |done| !*done);
+	true.into()
+}
+
 pub fn list_processes() -> Result<Vec<u32>, Error> {
 	let mut buf_entries = 32;
 	let mut read_bytes: u32 = u32::MAX;
@@ -132,7 +195,7 @@ impl ProcessHandle {
 impl Drop for ProcessHandle {
 	fn drop(&mut self) {
 		if let Err(err) = unsafe { CloseHandle(self.raw) } {
-			eprintln!("Error closing handle: {}", err);
+			error!("Error closing handle: {}", err);
 		}
 	}
 }
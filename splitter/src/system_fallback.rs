@@ -0,0 +1,19 @@
+// Stand-ins for system.rs on other platforms, where the command line works but the game can't be hooked
+
+use std::io::{Error, ErrorKind};
+
+/// Terminals are already attached everywhere but Windows
+pub fn attach_console() {}
+
+pub fn catch_console_stop() -> Result<(), Error> {
+	Err(Error::new(ErrorKind::Unsupported, "only supported on Windows"))
+}
+
+pub fn stop_requested() -> bool {
+	false
+}
+
+pub fn stopped() {}
+
+/// Already printed to stderr, there's no message box to show it in
+pub fn show_error(_title: &str, _message: &str) {}
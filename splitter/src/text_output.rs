@@ -0,0 +1,128 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs, io,
+	path::{Path, PathBuf},
+	sync::mpsc::{self, Sender},
+	thread,
+};
+
+use log::{info, warn};
+
+use crate::{ZeroError, api::LiveState};
+
+/// Handle to the thread writing text files for OBS text sources
+pub struct TextOutput {
+	tx: Sender<LiveState>,
+}
+
+impl TextOutput {
+	/// Write the files into `dir` from a background thread, creating it if needed
+	pub fn start(dir: PathBuf) -> Result<Self, ZeroError> {
+		fs::create_dir_all(&dir)?;
+		info!("Writing text files to {}", dir.display());
+		let (tx, rx) = mpsc::channel::<LiveState>();
+		thread::Builder::new().name("text files".to_owned()).spawn(move || {
+			let mut written = HashMap::new();
+			// Files that failed last time, to warn once until they work again instead of every frame
+			let mut failing = HashSet::new();
+			while let Ok(mut state) = rx.recv() {
+				// Only the newest state matters
+				while let Ok(newer) = rx.try_recv() {
+					state = newer;
+				}
+				for (name, text) in files(&state) {
+					if written.get(name) == Some(&text) {
+						continue;
+					}
+					match write(&dir, name, &text) {
+						Ok(()) => {
+							written.insert(name, text);
+							failing.remove(name);
+						}
+						Err(err) => {
+							if failing.insert(name) {
+								warn!("Couldn't write {name} in {}: {err}", dir.display());
+							}
+						}
+					}
+				}
+			}
+		})?;
+		Ok(Self { tx })
+	}
+
+	pub fn publish(&self, state: LiveState) {
+		let _ = self.tx.send(state);
+	}
+}
+
+/// Replace a file in one go, so OBS never reads it empty or half written
+fn write(dir: &Path, name: &str, text: &str) -> io::Result<()> {
+	let temp = dir.join(format!("{name}.tmp"));
+	fs::write(&temp, text)?;
+	fs::rename(&temp, dir.join(name))
+}
+
+/// Name and contents of every file
+fn files(state: &LiveState) -> [(&'static str, String); 6] {
+	// The delta of the last split that was finished, the current one is still going
+	let delta = state
+		.current_split
+		.filter(|_| state.active)
+		.and_then(|current| current.checked_sub(1))
+		.and_then(|last| state.splits.get(last))
+		.and_then(|split| split.delta)
+		.map(|delta| format!("{delta:+}"))
+		.unwrap_or_default();
+
+	[
+		("split.txt", state.current_split_name.clone().unwrap_or_default()),
+		("score.txt", state.score.to_string()),
+		("delta.txt", delta),
+		("pb.txt", state.pb.to_string()),
+		("sum_of_best.txt", state.sum_of_best.to_string()),
+		("attempts.txt", state.attempts.to_string()),
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::api::SplitState;
+
+	#[test]
+	fn delta_of_last_split() {
+		let split = |split: usize, delta: Option<i32>| SplitState {
+			split,
+			name: format!("1-{}", split + 1),
+			score: 10,
+			total: 10 * (split as i32 + 1),
+			comparison: 10,
+			comparison_total: 10,
+			delta,
+			best: 20,
+		};
+		let mut state = LiveState::example();
+		assert_eq!(files(&state)[2], ("delta.txt", String::new()));
+
+		state.active = true;
+		state.current_split = Some(1);
+		state.current_split_name = Some("1-2".to_owned());
+		state.splits = vec![split(0, Some(-5)), split(1, Some(3))];
+		let files = files(&state);
+		assert_eq!(files[0], ("split.txt", "1-2".to_owned()));
+		assert_eq!(files[2], ("delta.txt", "-5".to_owned()));
+		assert_eq!(files[5], ("attempts.txt", "2".to_owned()));
+	}
+
+	#[test]
+	fn replaces_files() -> io::Result<()> {
+		let dir = std::env::temp_dir().join(format!("zerosplitter-text-{}", std::process::id()));
+		fs::create_dir_all(&dir)?;
+		write(&dir, "score.txt", "1000")?;
+		write(&dir, "score.txt", "20")?;
+		assert_eq!(fs::read_to_string(dir.join("score.txt"))?, "20");
+		assert!(!dir.join("score.txt.tmp").exists());
+		fs::remove_dir_all(&dir)
+	}
+}
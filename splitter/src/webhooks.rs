@@ -0,0 +1,339 @@
+use std::{
+	sync::mpsc::{Receiver, RecvTimeoutError},
+	thread,
+	time::{Duration, Instant},
+};
+
+use log::{debug, error, warn};
+use reqwest::{blocking::Client, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{ZeroError, events::RunEvent};
+
+/// Tries for each notification before it's dropped
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry, doubled for each one after
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// One `webhooks` entry in the config
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+	pub url: String,
+	/// What to send a notification for, everything if it isn't set
+	#[serde(default = "WebhookEvent::all")]
+	pub events: Vec<WebhookEvent>,
+	/// Run score that fires `milestone` the first time a run reaches it
+	pub milestone: Option<i32>,
+	/// Body of the request, with `{event}`, `{category}`, `{split}`, `{score}`, `{previous}` and `{diff}` filled in.
+	/// A JSON object with every field otherwise.
+	pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+	PersonalBest,
+	Gold,
+	Clear,
+	Milestone,
+}
+
+impl WebhookEvent {
+	fn all() -> Vec<Self> {
+		vec![
+			WebhookEvent::PersonalBest,
+			WebhookEvent::Gold,
+			WebhookEvent::Clear,
+			WebhookEvent::Milestone,
+		]
+	}
+}
+
+/// What a webhook is told
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Notification {
+	event: WebhookEvent,
+	category: String,
+	/// The split a gold or milestone happened on
+	split: Option<String>,
+	/// The run's score, or the split's for a gold
+	score: i32,
+	/// The score that was beaten: the old PB, the old gold or the milestone. None for a clear.
+	previous: Option<i32>,
+	diff: Option<i32>,
+}
+
+impl Notification {
+	fn body(&self, template: Option<&str>) -> Result<String, serde_json::Error> {
+		let Some(template) = template else {
+			return serde_json::to_string(self);
+		};
+		let Value::Object(fields) = serde_json::to_value(self)? else {
+			unreachable!("notifications serialise to objects")
+		};
+		let mut body = template.to_owned();
+		for (name, value) in fields {
+			let text = match value {
+				// Escaped without the quotes, so it can go inside a JSON string in the template
+				Value::String(_) => {
+					let quoted = value.to_string();
+					quoted[1..quoted.len() - 1].to_owned()
+				}
+				Value::Null => String::new(),
+				value => value.to_string(),
+			};
+			body = body.replace(&format!("{{{name}}}"), &text);
+		}
+		// Catch templates that don't make JSON before anything gets sent
+		serde_json::from_str::<Value>(&body)?;
+		Ok(body)
+	}
+}
+
+/// Turns run events into notifications. The events don't all say which category they're in, so it follows along.
+#[derive(Debug, Default)]
+struct Tracker {
+	category: String,
+	last_split: Option<String>,
+	/// Milestones already announced this run, by webhook
+	reached: Vec<bool>,
+}
+
+impl Tracker {
+	/// Every notification for an event, with the index of the webhook it goes to
+	fn notifications(&mut self, hooks: &[Webhook], event: &RunEvent) -> Vec<(usize, Notification)> {
+		let mut found = Vec::new();
+		// Run totals don't come with every frame, so milestones are noticed at the end of a split or the run
+		let mut milestone_total = None;
+		match event {
+			RunEvent::Started { category, .. } => {
+				self.category = category.clone();
+				self.last_split = None;
+				self.reached = vec![false; hooks.len()];
+			}
+			RunEvent::CategoryChanged { category, .. } => self.category = category.clone(),
+			RunEvent::Split { name, total, .. } => {
+				self.last_split = Some(name.clone());
+				milestone_total = Some(*total);
+			}
+			RunEvent::Gold {
+				name, score, previous, ..
+			} => found.push(self.notification(WebhookEvent::Gold, Some(name), *score, Some(*previous))),
+			RunEvent::PersonalBest { total, previous } => {
+				found.push(self.notification(WebhookEvent::PersonalBest, None, *total, Some(*previous)))
+			}
+			RunEvent::Ended { total, cleared, .. } => {
+				milestone_total = Some(*total);
+				if *cleared {
+					found.push(self.notification(WebhookEvent::Clear, None, *total, None));
+				}
+			}
+			RunEvent::HookStatus { .. } => {}
+		}
+
+		let mut sends = Vec::new();
+		for (idx, hook) in hooks.iter().enumerate() {
+			sends.extend(
+				found
+					.iter()
+					.filter(|n| hook.events.contains(&n.event))
+					.map(|n| (idx, n.clone())),
+			);
+			if let (Some(total), Some(milestone)) = (milestone_total, hook.milestone)
+				&& total >= milestone
+				&& hook.events.contains(&WebhookEvent::Milestone)
+				&& self.reached.get(idx) == Some(&false)
+			{
+				let n = self.notification(
+					WebhookEvent::Milestone,
+					self.last_split.as_ref(),
+					total,
+					Some(milestone),
+				);
+				sends.push((idx, n));
+				self.reached[idx] = true;
+			}
+		}
+		sends
+	}
+
+	fn notification(
+		&self,
+		event: WebhookEvent,
+		split: Option<&String>,
+		score: i32,
+		previous: Option<i32>,
+	) -> Notification {
+		Notification {
+			event,
+			category: self.category.clone(),
+			split: split.cloned(),
+			score,
+			previous,
+			diff: previous.map(|previous| score - previous),
+		}
+	}
+}
+
+/// A request waiting to be sent
+struct Delivery {
+	url: String,
+	body: String,
+	attempts: u32,
+	due: Instant,
+}
+
+/// Send notifications to `hooks` from a background thread
+pub fn start(hooks: Vec<Webhook>, events: Receiver<RunEvent>) -> Result<(), ZeroError> {
+	let client = Client::builder()
+		.user_agent("ZeroSplitter")
+		.timeout(Duration::from_secs(10))
+		.build()?;
+	thread::Builder::new()
+		.name("webhooks".to_owned())
+		.spawn(move || deliver(&client, &hooks, &events, RETRY_DELAY))?;
+	Ok(())
+}
+
+/// Queue notifications as events come in, and send them until `events` is closed and nothing is left to retry
+fn deliver(client: &Client, hooks: &[Webhook], events: &Receiver<RunEvent>, retry_delay: Duration) {
+	let mut tracker = Tracker::default();
+	let mut queue: Vec<Delivery> = Vec::new();
+	let mut open = true;
+	while open || !queue.is_empty() {
+		let next_due = queue.iter().map(|d| d.due).min();
+		let received = match next_due {
+			_ if !open => None,
+			Some(due) => match events.recv_timeout(due.saturating_duration_since(Instant::now())) {
+				Ok(event) => Some(event),
+				Err(RecvTimeoutError::Timeout) => None,
+				Err(RecvTimeoutError::Disconnected) => {
+					open = false;
+					None
+				}
+			},
+			None => events.recv().inspect_err(|_| open = false).ok(),
+		};
+		if !open && let Some(due) = next_due {
+			thread::sleep(due.saturating_duration_since(Instant::now()));
+		}
+
+		if let Some(event) = received {
+			for (idx, notification) in tracker.notifications(hooks, &event) {
+				let hook = &hooks[idx];
+				match notification.body(hook.template.as_deref()) {
+					Ok(body) => queue.push(Delivery {
+						url: hook.url.clone(),
+						body,
+						attempts: 0,
+						due: Instant::now(),
+					}),
+					Err(err) => error!("Webhook template for {} doesn't make JSON: {err}", hook.url),
+				}
+			}
+		}
+
+		let now = Instant::now();
+		queue.retain_mut(|delivery| {
+			if delivery.due > now {
+				return true;
+			}
+			let sent = client
+				.post(&delivery.url)
+				.header(CONTENT_TYPE, "application/json")
+				.body(delivery.body.clone())
+				.send()
+				.and_then(|response| response.error_for_status());
+			match sent {
+				Ok(_) => {
+					debug!("Sent webhook to {}", delivery.url);
+					false
+				}
+				Err(err) => {
+					delivery.attempts += 1;
+					if delivery.attempts >= MAX_ATTEMPTS {
+						error!("Giving up on webhook to {}: {err}", delivery.url);
+						return false;
+					}
+					warn!("Webhook to {} failed, trying again: {err}", delivery.url);
+					delivery.due = Instant::now() + retry_delay * 2u32.pow(delivery.attempts - 1);
+					true
+				}
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::mpsc;
+
+	use tiny_http::{Response, Server};
+
+	use super::*;
+
+	#[test]
+	fn retries_until_delivered() -> Result<(), ZeroError> {
+		// Stands in for Discord, failing the first time
+		let server = Server::http("127.0.0.1:0").unwrap();
+		let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+		let hooks = vec![Webhook {
+			url,
+			events: vec![WebhookEvent::PersonalBest, WebhookEvent::Milestone],
+			milestone: Some(1000),
+			template: Some(r#"{"content": "{event} in {category}: {score} ({diff})"}"#.to_owned()),
+		}];
+
+		let (tx, rx) = mpsc::channel();
+		let sender = thread::spawn(move || deliver(&Client::new(), &hooks, &rx, Duration::from_millis(10)));
+		tx.send(RunEvent::Started {
+			category: "\"GO\"".to_owned(),
+			split: 0,
+			name: "1-1".to_owned(),
+			comparison: "Personal Best".to_owned(),
+		})
+		.unwrap();
+		// not a PB, and gold isn't subscribed to
+		tx.send(RunEvent::Gold {
+			split: 0,
+			name: "1-1".to_owned(),
+			score: 600,
+			previous: 500,
+		})
+		.unwrap();
+		tx.send(RunEvent::PersonalBest {
+			total: 1200,
+			previous: 1100,
+		})
+		.unwrap();
+		tx.send(RunEvent::Ended {
+			total: 1200,
+			split: 1,
+			pb: true,
+			cleared: false,
+		})
+		.unwrap();
+		drop(tx);
+
+		let mut bodies = Vec::new();
+		for status in [500, 200, 200] {
+			let mut request = server
+				.recv_timeout(Duration::from_secs(10))?
+				.expect("webhook wasn't sent");
+			let mut body = String::new();
+			request.as_reader().read_to_string(&mut body)?;
+			request.respond(Response::empty(status))?;
+			bodies.push(body);
+		}
+		sender.join().unwrap();
+
+		// the retry can come before or after the milestone
+		bodies.sort();
+		let pb = r#"{"content": "personal_best in \"GO\": 1200 (100)"}"#;
+		assert_eq!(bodies, [r#"{"content": "milestone in \"GO\": 1200 (200)"}"#, pb, pb]);
+		Ok(())
+	}
+}
@@ -0,0 +1,103 @@
+use std::{
+	sync::mpsc::{self, Receiver, Sender, TryRecvError},
+	thread,
+};
+
+use crate::{ZeroError, database::Database};
+
+type Job = Box<dyn FnOnce(&Database) + Send>;
+
+/// Owns the database on its own thread, so a slow query or a locked file can't freeze the UI.
+/// Jobs run one at a time, in the order they were sent.
+pub struct DbWorker {
+	jobs: Sender<Job>,
+}
+
+impl DbWorker {
+	/// Start the worker thread and open the database on it.
+	/// Fails with the same errors as [`Database::init`].
+	pub fn spawn() -> Result<Self, ZeroError> {
+		let (jobs, job_rx) = mpsc::channel::<Job>();
+		let (init_tx, init_rx) = mpsc::channel();
+
+		thread::Builder::new().name("database".to_owned()).spawn(move || {
+			let db = match Database::init() {
+				Ok(db) => db,
+				Err(err) => {
+					let _ = init_tx.send(Err(err));
+					return;
+				}
+			};
+			let _ = init_tx.send(Ok(()));
+			// Stops once every DbWorker handle is dropped
+			for job in job_rx {
+				job(&db);
+			}
+		})?;
+
+		init_rx.recv().map_err(|_| ZeroError::DatabaseClosed)??;
+		Ok(Self { jobs })
+	}
+
+	/// Queue a job without waiting for it. Check on it with [`Pending::poll`].
+	pub fn request<T: Send + 'static>(
+		&self,
+		job: impl FnOnce(&Database) -> Result<T, ZeroError> + Send + 'static,
+	) -> Pending<T> {
+		let (tx, rx) = mpsc::channel();
+		// If the worker is gone the job is dropped with `tx`, which the Pending sees as DatabaseClosed
+		let _ = self.jobs.send(Box::new(move |db| {
+			let _ = tx.send(job(db));
+		}));
+		Pending { rx }
+	}
+
+	/// Run a job and wait for the result.
+	/// For things the user clicked on, not for anything that runs every frame.
+	pub fn call<T: Send + 'static>(
+		&self,
+		job: impl FnOnce(&Database) -> Result<T, ZeroError> + Send + 'static,
+	) -> Result<T, ZeroError> {
+		self.request(job).wait()
+	}
+}
+
+/// A job sent to the [`DbWorker`] that may not have finished yet
+pub struct Pending<T> {
+	rx: Receiver<Result<T, ZeroError>>,
+}
+
+impl<T> Pending<T> {
+	/// The job's result if it's done, `None` if it's still queued or running.
+	/// Only returns the result once.
+	pub fn poll(&self) -> Option<Result<T, ZeroError>> {
+		match self.rx.try_recv() {
+			Ok(result) => Some(result),
+			Err(TryRecvError::Empty) => None,
+			Err(TryRecvError::Disconnected) => Some(Err(ZeroError::DatabaseClosed)),
+		}
+	}
+
+	pub fn wait(self) -> Result<T, ZeroError> {
+		self.rx.recv().unwrap_or(Err(ZeroError::DatabaseClosed))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn jobs_run_in_order() -> Result<(), ZeroError> {
+		let worker = DbWorker::spawn()?;
+
+		let insert = worker.request(|db| Ok(db.import_run(vec![10, 20], &"default".to_string())?));
+		let count = worker.call(|db| Ok(db.get_split_records("default")?.len()))?;
+		assert_eq!(count, 2);
+		assert!(matches!(insert.poll(), Some(Ok(()))));
+
+		let failed = worker.call(|db| Ok(db.import_run(vec![1], &"missing".to_string())?));
+		assert!(matches!(failed, Err(ZeroError::DatabaseError(_))));
+		Ok(())
+	}
+}